@@ -0,0 +1 @@
+30
\ No newline at end of file
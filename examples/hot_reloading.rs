@@ -42,7 +42,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     loop {
         cache.hot_reload();
 
-        print!("{}\n", x.read().0);
+        println!("{}", x.read().0);
 
         sleep(Duration::from_millis(200));
     }
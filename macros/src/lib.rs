@@ -0,0 +1,175 @@
+//! Procedural macros for `assets_manager`.
+//!
+//! You should not use this crate directly, but the reexports of
+//! `assets_manager` instead.
+
+#![warn(missing_docs)]
+
+extern crate proc_macro;
+
+use proc_macro::{TokenStream, TokenTree};
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+
+/// Embeds a directory in the binary.
+///
+/// See `assets_manager::source::Embedded` for more informations.
+#[proc_macro]
+pub fn embed(input: TokenStream) -> TokenStream {
+    let result = parse_path(input).and_then(|path| {
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        walk(&path, String::new(), &mut files, &mut dirs)?;
+        Ok(generate(&files, &dirs))
+    });
+
+    match result {
+        Ok(code) => code,
+        Err(msg) => format!("compile_error!({:?})", msg),
+    }.parse().unwrap()
+}
+
+/// Parses a string literal and makes it absolute relatively to the root of
+/// the calling crate.
+fn parse_path(input: TokenStream) -> Result<PathBuf, String> {
+    let mut tokens = input.into_iter();
+
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(lit)), None) => lit.to_string(),
+        _ => return Err("expected a string literal".into()),
+    };
+    let path = unquote(&literal).ok_or("expected a string literal")?;
+
+    let root = env::var_os("CARGO_MANIFEST_DIR").ok_or("CARGO_MANIFEST_DIR is not set")?;
+    let path = Path::new(&root).join(path);
+
+    path.canonicalize().map_err(|err| format!("invalid path {:?}: {}", path, err))
+}
+
+/// Returns the content of a string literal.
+fn unquote(literal: &str) -> Option<String> {
+    fn between_quotes(s: &str) -> Option<&str> {
+        if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+            Some(&s[1..s.len() - 1])
+        } else {
+            None
+        }
+    }
+
+    if let Some(raw) = literal.strip_prefix('r') {
+        return between_quotes(raw.trim_matches('#')).map(str::to_owned);
+    }
+
+    let inner = between_quotes(literal)?;
+
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                'n' => result.push('\n'),
+                't' => result.push('\t'),
+                'r' => result.push('\r'),
+                '0' => result.push('\0'),
+                c @ '\\' | c @ '"' | c @ '\'' => result.push(c),
+                _ => return None,
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    Some(result)
+}
+
+fn id_push(id: &mut String, name: &str) {
+    if !id.is_empty() {
+        id.push('.');
+    }
+    id.push_str(name);
+}
+
+struct File {
+    id: String,
+    ext: String,
+    path: String,
+}
+
+enum Entry {
+    File(String, String),
+    Dir(String),
+}
+
+/// Recursively lists files and directories, computing ids the same way as
+/// `assets_manager::source::FileSystem`.
+fn walk(path: &Path, id: String, files: &mut Vec<File>, dirs: &mut Vec<(String, Vec<Entry>)>) -> Result<(), String> {
+    let mut entries = Vec::new();
+
+    let read_dir = fs::read_dir(path).map_err(|err| format!("cannot read {:?}: {}", path, err))?;
+    let mut paths: Vec<_> = read_dir.filter_map(|entry| Some(entry.ok()?.path())).collect();
+    paths.sort();
+
+    for path in paths {
+        let name = match path.file_stem().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let mut this_id = id.clone();
+        id_push(&mut this_id, name);
+
+        if path.is_file() {
+            let ext = match path.extension() {
+                Some(ext) => match ext.to_str() {
+                    Some(ext) => ext,
+                    None => continue,
+                },
+                None => "",
+            };
+
+            let file_path = path.to_str().ok_or_else(|| format!("non UTF-8 path: {:?}", path))?;
+
+            entries.push(Entry::File(this_id.clone(), ext.to_owned()));
+            files.push(File { id: this_id, ext: ext.to_owned(), path: file_path.to_owned() });
+        } else if path.is_dir() {
+            entries.push(Entry::Dir(this_id.clone()));
+            walk(&path, this_id, files, dirs)?;
+        }
+    }
+
+    dirs.push((id, entries));
+    Ok(())
+}
+
+fn generate(files: &[File], dirs: &[(String, Vec<Entry>)]) -> String {
+    const SOURCE: &str = "::assets_manager::source";
+
+    let mut code = String::new();
+
+    write!(code, "{{ static FILES: &[((&str, &str), &[u8])] = &[").unwrap();
+    for file in files {
+        write!(code, "(({:?}, {:?}), include_bytes!({:?})),", file.id, file.ext, file.path).unwrap();
+    }
+
+    write!(code, "]; static DIRS: &[(&str, &[{}::DirEntry])] = &[", SOURCE).unwrap();
+    for (id, entries) in dirs {
+        write!(code, "({:?}, &[", id).unwrap();
+        for entry in entries {
+            match entry {
+                Entry::File(id, ext) => write!(code, "{}::DirEntry::File({:?}, {:?}),", SOURCE, id, ext),
+                Entry::Dir(id) => write!(code, "{}::DirEntry::Directory({:?}),", SOURCE, id),
+            }.unwrap();
+        }
+        code.push_str("]),");
+    }
+
+    write!(code, "]; {}::Embedded::from({}::RawEmbedded {{ files: FILES, dirs: DIRS }}) }}", SOURCE, SOURCE).unwrap();
+
+    code
+}
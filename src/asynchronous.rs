@@ -0,0 +1,358 @@
+//! Asynchronous loading of assets
+
+use crate::{
+    Asset,
+    AssetCache,
+    AssetError,
+    AssetRef,
+    cache::{AccessKey, Key, load_sized},
+    dirs::{CachedDir, DirReader, list_dir},
+    source::Source,
+    utils::{HashMap, Mutex},
+};
+
+#[cfg(not(feature = "rayon"))]
+use std::collections::VecDeque;
+
+use std::{
+    any::Any,
+    future::Future,
+    io,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+#[cfg(not(feature = "rayon"))]
+use std::thread;
+
+/// Assets being loaded, so that concurrent requests share the same task.
+pub(crate) type PendingLoads = HashMap<Key, Arc<dyn Any + Send + Sync>>;
+
+type LoadTask<A> = Task<Result<(A, usize), Arc<AssetError<A>>>>;
+
+
+enum State<T> {
+    Running(Vec<Waker>),
+    Done(Option<T>),
+    Panicked,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The threads that run the blocking part of asynchronous loads.
+///
+/// With feature `rayon`, jobs are run on *rayon*'s global thread pool.
+/// Otherwise, threads are started when jobs are queued, up to a limit, and
+/// stop when there is no job left.
+pub(crate) struct Workers {
+    #[cfg(not(feature = "rayon"))]
+    queue: Arc<Mutex<Queue>>,
+}
+
+#[cfg(not(feature = "rayon"))]
+struct Queue {
+    jobs: VecDeque<Job>,
+    threads: usize,
+}
+
+impl Workers {
+    /// The maximum number of threads used by a cache.
+    #[cfg(not(feature = "rayon"))]
+    const MAX_THREADS: usize = 8;
+
+    pub fn new() -> Self {
+        Workers {
+            #[cfg(not(feature = "rayon"))]
+            queue: Arc::new(Mutex::new(Queue {
+                jobs: VecDeque::new(),
+                threads: 0,
+            })),
+        }
+    }
+
+    /// Runs a job on one of the threads.
+    ///
+    /// Jobs are expected not to panic.
+    #[cfg(feature = "rayon")]
+    fn execute(&self, job: Job) {
+        rayon::spawn(job);
+    }
+
+    /// Runs a job on one of the threads.
+    ///
+    /// Jobs are expected not to panic.
+    #[cfg(not(feature = "rayon"))]
+    fn execute(&self, job: Job) {
+        let mut queue = self.queue.lock();
+        queue.jobs.push_back(job);
+
+        if queue.threads < Self::MAX_THREADS {
+            queue.threads += 1;
+            let shared = self.queue.clone();
+            thread::spawn(move || Self::run(&shared));
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn run(queue: &Mutex<Queue>) {
+        loop {
+            let job = {
+                let mut queue = queue.lock();
+                match queue.jobs.pop_front() {
+                    Some(job) => job,
+                    None => {
+                        queue.threads -= 1;
+                        return;
+                    },
+                }
+            };
+            job();
+        }
+    }
+}
+
+
+/// A blocking function run on another thread, which can be awaited by several
+/// futures.
+pub(crate) struct Task<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Runs `f` on one of the given workers.
+    fn spawn(workers: &Workers, f: impl FnOnce() -> T + Send + 'static) -> Arc<Self> {
+        let task = Arc::new(Task {
+            state: Mutex::new(State::Running(Vec::new())),
+        });
+
+        let this = task.clone();
+        workers.execute(Box::new(move || {
+            let state = match panic::catch_unwind(AssertUnwindSafe(f)) {
+                Ok(value) => State::Done(Some(value)),
+                Err(_) => State::Panicked,
+            };
+
+            let previous = mem::replace(&mut *this.state.lock(), state);
+            if let State::Running(wakers) = previous {
+                wakers.into_iter().for_each(Waker::wake);
+            }
+        }));
+
+        task
+    }
+}
+
+/// The function run by a task panicked.
+struct Panicked;
+
+impl<T> Task<T> {
+    /// Waits for the task to complete, then calls `f` with its result.
+    ///
+    /// The result is shared between all callers, which are responsible for
+    /// taking it or not. An error is returned if the task panicked.
+    fn with<F, R>(&self, f: F) -> With<'_, T, F>
+    where
+        F: FnOnce(&mut Option<T>) -> R,
+    {
+        With {
+            task: self,
+            f: Some(f),
+        }
+    }
+}
+
+struct With<'a, T, F> {
+    task: &'a Task<T>,
+    f: Option<F>,
+}
+
+// We never create a `Pin<&mut F>`
+impl<T, F> Unpin for With<'_, T, F> {}
+
+impl<T, F, R> Future for With<'_, T, F>
+where
+    F: FnOnce(&mut Option<T>) -> R,
+{
+    type Output = Result<R, Panicked>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut state = this.task.state.lock();
+
+        match &mut *state {
+            State::Running(wakers) => {
+                if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            },
+            State::Done(value) => {
+                let f = this.f.take().expect("`With` polled after completion");
+                Poll::Ready(Ok(f(value)))
+            },
+            State::Panicked => Poll::Ready(Err(Panicked)),
+        }
+    }
+}
+
+
+/// Removes a task from pending loads when dropped, ie when its result was
+/// received or when the future waiting for it was dropped.
+struct Unpending<'a, A: Asset, S> {
+    cache: &'a AssetCache<S>,
+    id: &'a str,
+    task: &'a LoadTask<A>,
+}
+
+impl<A: Asset, S> Drop for Unpending<'_, A, S> {
+    fn drop(&mut self) {
+        let mut pending = self.cache.pending.lock();
+        let key = AccessKey::new::<A>(self.id);
+
+        // The task may already have been replaced by another one
+        let is_same = match pending.get(&key) {
+            Some(other) => &**other as *const dyn Any as *const () == self.task as *const LoadTask<A> as *const (),
+            None => false,
+        };
+        if is_same {
+            pending.remove(&key);
+        }
+    }
+}
+
+
+impl<S> AssetCache<S>
+where
+    S: Source + Send + Sync + 'static,
+{
+    /// Starts loading an asset on another thread, unless it is already being
+    /// loaded.
+    fn start_loading<A: Asset>(&self, id: &str) -> Arc<LoadTask<A>>
+    where
+        AssetError<A>: Send + Sync + 'static,
+    {
+        let mut pending = self.pending.lock();
+
+        let task = pending.entry(Key::new::<A>(id.into())).or_insert_with(|| {
+            self.watch_file::<A>(id);
+
+            let source = self.source.clone();
+            let id: Box<str> = id.into();
+            Task::spawn(&self.workers, move || load_sized::<A, S>(&*source, &id).map_err(Arc::new))
+        });
+
+        match task.clone().downcast() {
+            Ok(task) => task,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Waits for an asset to be loaded and adds it to the cache.
+    ///
+    /// Returns `None` if the asset was already taken by another caller.
+    async fn finish_loading<A: Asset>(&self, id: &str, task: Arc<LoadTask<A>>) -> Option<Result<AssetRef<'_, A>, Arc<AssetError<A>>>> {
+        let unpending = Unpending { cache: self, id, task: &task };
+
+        // The asset is inserted in the cache while the task is locked, so
+        // other callers can find it there when they see that the result was
+        // taken. Errors are kept for all callers.
+        let result = task.with(|result| match result.take()? {
+            Ok((asset, size)) => Some(Ok(self.insert_asset(id.into(), asset, size))),
+            Err(err) => {
+                *result = Some(Err(err.clone()));
+                Some(Err(err))
+            },
+        }).await;
+
+        // The task is removed first, so that later calls try loading again
+        drop(unpending);
+        match result {
+            Ok(result) => result,
+            Err(Panicked) => panic!("Loading thread panicked"),
+        }
+    }
+
+    /// Loads an asset asynchronously.
+    ///
+    /// If the asset is not found in the cache, it is loaded from the source
+    /// on a pool of background threads, so the returned future never blocks.
+    /// This works with any executor. With feature `rayon`, *rayon*'s global
+    /// thread pool is used.
+    ///
+    /// If several tasks try to load the same asset at the same time, it is only
+    /// loaded once, and all of them get the result. Errors are not cached, so
+    /// later calls try loading the asset again.
+    ///
+    /// # Errors
+    ///
+    /// Error cases are the same as [`load`]. As the error is shared by all
+    /// tasks waiting for the asset, it is returned in an `Arc`.
+    ///
+    /// # Panics
+    ///
+    /// The future panics if the [`Source`] or the [`Loader`] panics. Later
+    /// calls try loading the asset again.
+    ///
+    /// [`load`]: #method.load
+    /// [`Source`]: source/trait.Source.html
+    /// [`Loader`]: loader/trait.Loader.html
+    pub async fn load_async<A: Asset>(&self, id: &str) -> Result<AssetRef<'_, A>, Arc<AssetError<A>>>
+    where
+        AssetError<A>: Send + Sync + 'static,
+    {
+        loop {
+            if let Some(asset) = self.load_cached(id) {
+                return Ok(asset);
+            }
+
+            let task = self.start_loading::<A>(id);
+            if let Some(result) = self.finish_loading(id, task).await {
+                return result;
+            }
+        }
+    }
+
+    /// Loads all assets of a given type in a directory asynchronously.
+    ///
+    /// This is the asynchronous version of [`load_dir`]. The assets of the
+    /// directory are loaded concurrently, see [`load_async`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    ///
+    /// [`load_dir`]: #method.load_dir
+    /// [`load_async`]: #method.load_async
+    pub async fn load_dir_async<A: Asset>(&self, id: &str) -> io::Result<DirReader<'_, A, S>>
+    where
+        AssetError<A>: Send + Sync + 'static,
+    {
+        if let Some(dir) = self.get_cached_dir(&AccessKey::new::<A>(id)) {
+            return Ok(dir);
+        }
+
+        let source = self.source.clone();
+        let dir_id: Box<str> = id.into();
+        let task = Task::spawn(&self.workers, move || list_dir::<A, S>(&*source, &dir_id, false));
+        let ids = match task.with(Option::take).await {
+            Ok(ids) => ids.expect("result taken twice")?,
+            Err(Panicked) => panic!("Loading thread panicked"),
+        };
+
+        // Start all loads before waiting for any of them
+        let tasks: Vec<_> = ids.iter()
+            .filter(|id| self.load_cached::<A>(id).is_none())
+            .map(|id| (id, self.start_loading::<A>(id)))
+            .collect();
+
+        for (id, task) in tasks {
+            let _ = self.finish_loading(id, task).await;
+        }
+
+        self.watch_dir::<A>(id);
+        Ok(self.insert_dir(Key::new::<A>(id.into()), CachedDir::new(ids)))
+    }
+}
@@ -0,0 +1,272 @@
+//! Loading of many assets in the background
+
+use crate::{
+    Asset,
+    AssetCache,
+    source::Source,
+    utils::Mutex,
+};
+
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc,
+        Condvar,
+        Mutex as StdMutex,
+        MutexGuard,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+type LoadFn<S> = fn(&AssetCache<S>, &str) -> Result<(), String>;
+
+fn load<A: Asset, S: Source>(cache: &AssetCache<S>, id: &str) -> Result<(), String> {
+    match cache.load::<A>(id) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+
+/// A list of assets to load in the background.
+///
+/// Assets are added to the batch with [`add`], and are then loaded by a pool
+/// of threads when [`start`] is called. The returned [`BatchProgress`] can be
+/// used to follow the loading, for example to display a loading screen.
+///
+/// Assets are loaded with [`AssetCache::load`], so they are available in the
+/// cache once loaded.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, LoadingBatch};
+/// use std::sync::Arc;
+/// # use assets_manager::{Asset, loader::{LoadFrom, ParseLoader}};
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+/// # impl Asset for X {
+/// #     const EXTENSION: &'static str = "x";
+/// #     type Loader = LoadFrom<i32, ParseLoader>;
+/// # }
+///
+/// let cache = Arc::new(AssetCache::new("assets")?);
+///
+/// let mut batch = LoadingBatch::new(cache.clone());
+/// batch.add::<X>("test.b").add::<X>("test.cache");
+/// let progress = batch.start();
+///
+/// while !progress.is_done() {
+///     println!("Loading: {}/{}", progress.completed(), progress.total());
+/// #   std::thread::yield_now();
+/// }
+///
+/// assert_eq!(progress.failed(), 0);
+/// assert_eq!(cache.load_cached::<X>("test.cache").unwrap().read().0, 42);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`add`]: #method.add
+/// [`start`]: #method.start
+/// [`BatchProgress`]: struct.BatchProgress.html
+/// [`AssetCache::load`]: struct.AssetCache.html#method.load
+pub struct LoadingBatch<S> {
+    cache: Arc<AssetCache<S>>,
+    jobs: Vec<(Box<str>, LoadFn<S>)>,
+    threads: usize,
+}
+
+impl<S> LoadingBatch<S>
+where
+    S: Source + Send + Sync + 'static,
+{
+    /// Creates an empty batch of assets to load in the given cache.
+    #[inline]
+    pub fn new(cache: Arc<AssetCache<S>>) -> Self {
+        Self {
+            cache,
+            jobs: Vec::new(),
+            threads: 4,
+        }
+    }
+
+    /// Adds an asset to load.
+    pub fn add<A: Asset>(&mut self, id: &str) -> &mut Self {
+        self.jobs.push((id.into(), load::<A, S>));
+        self
+    }
+
+    /// Sets the number of threads used to load assets.
+    ///
+    /// The default is 4.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is `0`.
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        assert!(threads > 0, "At least one thread is required");
+        self.threads = threads;
+        self
+    }
+
+    /// Returns the number of assets in the batch.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns `true` if the batch contains no asset.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Starts loading the assets in the background.
+    pub fn start(self) -> BatchProgress {
+        let progress = Arc::new(Progress {
+            total: self.jobs.len(),
+            completed: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            errors: Mutex::new(Vec::new()),
+            done: StdMutex::new(0),
+            condvar: Condvar::new(),
+        });
+
+        let jobs = Arc::new(Jobs {
+            cache: self.cache,
+            jobs: self.jobs,
+            next: AtomicUsize::new(0),
+        });
+
+        for _ in 0..self.threads.min(progress.total) {
+            let jobs = jobs.clone();
+            let progress = progress.clone();
+            thread::spawn(move || jobs.run(&progress));
+        }
+
+        BatchProgress(progress)
+    }
+}
+
+impl<S> fmt::Debug for LoadingBatch<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<_> = self.jobs.iter().map(|(id, _)| id).collect();
+
+        f.debug_struct("LoadingBatch")
+            .field("ids", &ids)
+            .field("threads", &self.threads)
+            .finish()
+    }
+}
+
+
+/// Assets shared between the threads of a batch.
+struct Jobs<S> {
+    cache: Arc<AssetCache<S>>,
+    jobs: Vec<(Box<str>, LoadFn<S>)>,
+    next: AtomicUsize,
+}
+
+impl<S: Source> Jobs<S> {
+    fn run(&self, progress: &Progress) {
+        while let Some((id, load)) = self.jobs.get(self.next.fetch_add(1, Ordering::Relaxed)) {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| load(&self.cache, id)))
+                .unwrap_or_else(|_| Err(String::from("loading panicked")));
+            progress.finish(id, result);
+        }
+    }
+}
+
+struct Progress {
+    total: usize,
+    completed: AtomicUsize,
+    failed: AtomicUsize,
+    errors: Mutex<Vec<(Box<str>, String)>>,
+
+    // `Condvar` requires a mutex from `std`
+    done: StdMutex<usize>,
+    condvar: Condvar,
+}
+
+impl Progress {
+    fn lock_done(&self) -> MutexGuard<'_, usize> {
+        self.done.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn finish(&self, id: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                self.completed.fetch_add(1, Ordering::Relaxed);
+            },
+            Err(err) => {
+                self.errors.lock().push((id.into(), err));
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            },
+        }
+
+        let mut done = self.lock_done();
+        *done += 1;
+        if *done == self.total {
+            self.condvar.notify_all();
+        }
+    }
+}
+
+
+/// The progress of a [`LoadingBatch`].
+///
+/// [`LoadingBatch`]: struct.LoadingBatch.html
+#[derive(Clone)]
+pub struct BatchProgress(Arc<Progress>);
+
+impl BatchProgress {
+    /// Returns the number of assets in the batch.
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.0.total
+    }
+
+    /// Returns the number of assets successfully loaded.
+    #[inline]
+    pub fn completed(&self) -> usize {
+        self.0.completed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of assets that failed to load.
+    #[inline]
+    pub fn failed(&self) -> usize {
+        self.0.failed.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if all assets of the batch were loaded or failed to.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        *self.0.lock_done() == self.0.total
+    }
+
+    /// Returns the ids of the assets that failed to load, with their error.
+    pub fn errors(&self) -> Vec<(Box<str>, String)> {
+        self.0.errors.lock().clone()
+    }
+
+    /// Blocks the current thread until all assets of the batch are loaded or
+    /// failed to.
+    pub fn wait(&self) {
+        let mut done = self.0.lock_done();
+        while *done < self.0.total {
+            done = self.0.condvar.wait(done).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl fmt::Debug for BatchProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchProgress")
+            .field("total", &self.total())
+            .field("completed", &self.completed())
+            .field("failed", &self.failed())
+            .finish()
+    }
+}
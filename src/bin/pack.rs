@@ -0,0 +1,61 @@
+//! Packs a directory into a file that can be read by
+//! `assets_manager::source::Pack`.
+//!
+//! Usage: `pack [--align <N>] <DIRECTORY> <OUTPUT>`
+
+use assets_manager::source::{FileSystem, PackBuilder};
+
+use std::{
+    env,
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    process,
+};
+
+const USAGE: &str = "usage: pack [--align <N>] <DIRECTORY> <OUTPUT>";
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut align = 1;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--align" => {
+                let n = args.next().ok_or(USAGE)?;
+                align = n.parse().map_err(|_| format!("invalid alignment: {}", n))?;
+                if !usize::is_power_of_two(align) {
+                    return Err("alignment must be a power of two".into());
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            },
+            _ => paths.push(arg),
+        }
+    }
+
+    let (dir, output) = match &paths[..] {
+        [dir, output] => (dir, output),
+        _ => return Err(USAGE.into()),
+    };
+
+    let mut builder = PackBuilder::new();
+    builder.align(align);
+    builder.add_source(&FileSystem::new(dir)?, "")?;
+
+    let mut out = BufWriter::new(File::create(output)?);
+    builder.write(&mut out)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
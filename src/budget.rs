@@ -0,0 +1,138 @@
+//! Limitation of the memory used by a cache
+
+use crate::{
+    cache::Key,
+    entry::CacheEntry,
+    map::AssetMap,
+};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const NO_LIMIT: usize = usize::MAX;
+
+
+/// Tracks the memory used by the assets of a cache.
+///
+/// Sizes are estimated with the length of the files assets are loaded from,
+/// and entries are evicted in least recently used order.
+pub(crate) struct Budget {
+    limit: AtomicUsize,
+    used: AtomicUsize,
+    clock: AtomicUsize,
+}
+
+impl Budget {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            limit: AtomicUsize::new(NO_LIMIT),
+            used: AtomicUsize::new(0),
+            clock: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a new tick, greater than all previous ones.
+    #[inline]
+    pub fn tick(&self) -> usize {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    #[inline]
+    pub fn limit(&self) -> Option<usize> {
+        match self.limit.load(Ordering::Relaxed) {
+            NO_LIMIT => None,
+            limit => Some(limit),
+        }
+    }
+
+    #[inline]
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.limit.store(limit.unwrap_or(NO_LIMIT), Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn exceeded(&self) -> bool {
+        self.used() > self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Records that an entry was added to the cache.
+    #[inline]
+    pub fn add(&self, entry: &CacheEntry) {
+        self.used.fetch_add(entry.size(), Ordering::Relaxed);
+    }
+
+    /// Records that an entry was removed from the cache.
+    #[inline]
+    pub fn remove(&self, entry: &CacheEntry) {
+        self.remove_size(entry.size());
+    }
+
+    #[inline]
+    pub fn remove_size(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    /// Changes the size of an entry of the cache.
+    #[inline]
+    pub fn resize(&self, entry: &CacheEntry, size: usize) {
+        let old = entry.set_size(size);
+        self.used.fetch_add(size, Ordering::Relaxed);
+        self.used.fetch_sub(old, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn clear(&self) {
+        self.used.store(0, Ordering::Relaxed);
+    }
+
+    /// Removes the least recently used entries that are not shared with a
+    /// `Handle` until the memory used fits in the budget.
+    ///
+    /// The entry of `keep`, which was just given to the user, is never
+    /// removed. Entries with a size of zero are not removed either, as it
+    /// would not reduce the memory used.
+    ///
+    /// Returns the removed entries, which have to be unwatched and retired.
+    pub fn evict(&self, assets: &AssetMap, keep: Option<&Key>) -> Vec<(Key, CacheEntry)> {
+        let mut evicted = Vec::new();
+        if !self.exceeded() {
+            return evicted;
+        }
+
+        let is_candidate = |key: &Key, entry: &CacheEntry| {
+            Some(key) != keep && entry.size() != 0 && !entry.is_shared()
+        };
+
+        // Shards are locked one at a time, so entries may change between the
+        // moment they are selected and the moment they are removed
+        let mut candidates = Vec::new();
+        for shard in assets.shards() {
+            candidates.extend(shard.read().iter()
+                .filter(|(key, entry)| is_candidate(key, entry))
+                .map(|(key, entry)| (entry.last_access(), key.clone()))
+            );
+        }
+        candidates.sort_unstable_by_key(|(last_access, _)| *last_access);
+
+        for (_, key) in candidates {
+            if !self.exceeded() {
+                break;
+            }
+
+            let mut shard = assets.write(&key);
+            if matches!(shard.get(&key), Some(entry) if is_candidate(&key, entry)) {
+                if let Some((key, entry)) = shard.remove_entry(&key) {
+                    self.remove(&entry);
+                    evicted.push((key, entry));
+                }
+            }
+        }
+
+        evicted
+    }
+}
@@ -2,26 +2,34 @@
 use crate::{
     Asset,
     AssetError,
-    dirs::{CachedDir, DirReader},
+    Compound,
+    asynchronous::{PendingLoads, Workers},
+    budget::Budget,
+    dirs::{self, CachedDir, DirReader, Filtered, Glob, Matcher, Recursive},
     loader::Loader,
-    entry::{CacheEntry, AssetRef},
-    utils::{HashMap, RwLock},
+    entry::{CacheEntry, AssetRef, Handle},
+    map::AssetMap,
+    source::{FileSystem, Source},
+    utils::{HashMap, Mutex, RwLock},
 };
 
 #[cfg(feature = "hot-reloading")]
 use crate::{
-    hot_reloading::{HotReloader, WatchedPaths},
-    utils::Mutex,
+    compound::{self, Dependency, Graph},
+    hot_reloading::{Change, HotReloadConfig, HotReloader, ReloadReport, Subscribers, WatchedPaths},
 };
+#[cfg(feature = "hot-reloading")]
+use std::sync::mpsc;
 
 use std::{
     any::TypeId,
     borrow::Borrow,
     error::Error,
     fmt,
-    fs,
     io,
-    path::{Path, PathBuf},
+    mem,
+    path::Path,
+    sync::Arc,
 };
 
 
@@ -30,7 +38,7 @@ use std::{
 /// **Note**: This definition has to kept in sync with [`AccessKey`]'s one.
 ///
 /// [`AccessKey`]: struct.AccessKey.html
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 #[repr(C)]
 pub(crate) struct Key {
     id: Box<str>,
@@ -40,7 +48,7 @@ pub(crate) struct Key {
 impl Key {
     /// Creates a Key with the given type and id.
     #[inline]
-    fn new<T: Asset>(id: Box<str>) -> Self {
+    pub fn new<T: 'static>(id: Box<str>) -> Self {
         Self {
             id,
             type_id: TypeId::of::<T>(),
@@ -58,6 +66,12 @@ impl Key {
     pub fn id(&self) -> &str {
         &self.id
     }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
 }
 
 /// A borrowed version of [`Key`]
@@ -73,7 +87,7 @@ pub(crate) struct AccessKey<'a> {
 impl<'a> AccessKey<'a> {
     /// Creates an AccessKey for the given type and id.
     #[inline]
-    fn new<T: Asset>(id: &'a str) -> Self {
+    pub fn new<T: 'static>(id: &'a str) -> Self {
         Self {
             id,
             type_id: TypeId::of::<T>(),
@@ -112,6 +126,9 @@ impl fmt::Debug for AccessKey<'_> {
 /// It uses interior mutability, so assets can be added in the cache without
 /// requiring a mutable reference, but one is required to remove an asset.
 ///
+/// Assets are loaded from a [`Source`]. By default, it is the [`FileSystem`],
+/// but another one can be given with [`AssetCache::with_source`].
+///
 /// Within the cache, assets are identified with their type and a string. This
 /// string is constructed from the asset path, remplacing `/` by `.` and removing
 /// the extension. Given that, you cannot use `.` in your file names except for
@@ -121,6 +138,10 @@ impl fmt::Debug for AccessKey<'_> {
 /// to surprising behaviour (especially with hot-reloading), and thus should be
 /// avoided.
 ///
+/// [`Source`]: source/trait.Source.html
+/// [`FileSystem`]: source/struct.FileSystem.html
+/// [`AssetCache::with_source`]: #method.with_source
+///
 /// # Example
 ///
 /// ```
@@ -165,130 +186,387 @@ impl fmt::Debug for AccessKey<'_> {
 /// # }}
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
-pub struct AssetCache {
-    path: PathBuf,
+pub struct AssetCache<S = FileSystem> {
+    pub(crate) source: Arc<S>,
 
-    pub(crate) assets: RwLock<HashMap<Key, CacheEntry>>,
+    pub(crate) assets: AssetMap,
     pub(crate) dirs: RwLock<HashMap<Key, CachedDir>>,
+    pub(crate) pending: Mutex<PendingLoads>,
+    pub(crate) workers: Workers,
+    pub(crate) budget: Budget,
+    pub(crate) retired: Mutex<Retired>,
 
     #[cfg(feature = "hot-reloading")]
-    reloader: Mutex<HotReloader>,
+    pub(crate) reloader: Option<HotReloader>,
     #[cfg(feature = "hot-reloading")]
     pub(crate) watched: Mutex<WatchedPaths>,
+    #[cfg(feature = "hot-reloading")]
+    pub(crate) compounds: Mutex<Graph<S>>,
+    #[cfg(feature = "hot-reloading")]
+    pub(crate) subscribers: Mutex<Subscribers>,
 }
 
-impl AssetCache {
-    /// Creates a new cache.
-    ///
-    /// Assets will be searched in the directory given by `path`. Symbolic links
-    /// will be followed.
+impl AssetCache<FileSystem> {
+    /// Creates a cache that loads assets from the given directory.
     ///
     /// # Errors
     ///
     /// An error will be returned if `path` is not valid readable directory or
     /// if hot-reloading failed to start (if feature `hot-reloading` is used).
     #[inline]
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<AssetCache, CacheError> {
-        let path = path.as_ref().canonicalize().map_err(ErrorKind::Io)?;
-        let _ = path.read_dir().map_err(ErrorKind::Io)?;
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<AssetCache<FileSystem>, CacheError> {
+        let source = FileSystem::new(path).map_err(ErrorKind::Io)?;
+        Self::with_source(source)
+    }
+
+    /// Gets the path of the cache's root.
+    ///
+    /// The path is currently given as absolute, but this may change in the future.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        self.source.root()
+    }
+}
 
+impl<S: Source> AssetCache<S> {
+    /// Creates a cache that loads assets from the given source.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if hot-reloading failed to start (if feature
+    /// `hot-reloading` is used).
+    pub fn with_source(source: S) -> Result<AssetCache<S>, CacheError> {
         #[cfg(feature = "hot-reloading")]
-        let reloader = Mutex::new(HotReloader::start(&path).map_err(ErrorKind::Notify)?);
+        let cache = Self::with_config(source, &HotReloadConfig::default())?;
 
-        Ok(AssetCache {
+        #[cfg(not(feature = "hot-reloading"))]
+        let cache = Self::from_parts(source);
+
+        Ok(cache)
+    }
 
-            assets: RwLock::new(HashMap::new()),
+    /// Creates a cache that loads assets from the given source, with the given
+    /// hot-reloading settings.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if hot-reloading failed to start.
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn with_config(source: S, config: &HotReloadConfig) -> Result<AssetCache<S>, CacheError> {
+        let reloader = source.watch_with(config).map_err(ErrorKind::Watch)?
+            .map(|watcher| HotReloader::start(watcher, config));
+
+        Ok(Self::from_parts(source, reloader))
+    }
+
+    fn from_parts(source: S, #[cfg(feature = "hot-reloading")] reloader: Option<HotReloader>) -> AssetCache<S> {
+        AssetCache {
+            source: Arc::new(source),
+
+            assets: AssetMap::new(),
             dirs: RwLock::new(HashMap::new()),
-            path,
+            pending: Mutex::new(HashMap::new()),
+            workers: Workers::new(),
+            budget: Budget::new(),
+            retired: Mutex::new(Retired::default()),
 
             #[cfg(feature = "hot-reloading")]
             reloader,
             #[cfg(feature = "hot-reloading")]
             watched: Mutex::new(WatchedPaths::new()),
-        })
+            #[cfg(feature = "hot-reloading")]
+            compounds: Mutex::new(Graph::new()),
+            #[cfg(feature = "hot-reloading")]
+            subscribers: Mutex::new(Subscribers::new()),
+        }
     }
 
-    /// Gets the path of the cache's root.
+    /// Returns a reference to the cache's [`Source`].
     ///
-    /// The path is currently given as absolute, but this may change in the future.
-    pub fn path(&self) -> &Path {
-        &self.path
+    /// [`Source`]: source/trait.Source.html
+    #[inline]
+    pub fn source(&self) -> &S {
+        &self.source
     }
 
-    fn path_of(&self, id: &str) -> PathBuf {
-        let mut path = self.path.clone();
-        path.extend(id.split('.'));
-        path
+    /// Registers a file to be watched for hot-reloading, if enabled.
+    #[inline]
+    pub(crate) fn watch_file<A: Asset>(&self, _id: &str) {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            self.watched.lock().add_file::<A>(_id.into());
+        }
     }
 
-    /// Adds an asset to the cache
-    pub(crate) fn add_asset<A: Asset>(&self, id: Box<str>) -> Result<AssetRef<A>, AssetError<A>> {
-        let path = self.path_of(&id);
+    /// Registers a directory to be watched for hot-reloading, if enabled.
+    #[inline]
+    pub(crate) fn watch_dir<A: Asset>(&self, _id: &str) {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            self.watched.lock().add_dir::<A>(_id.into());
+        }
+    }
 
+    /// Registers a directory and its subdirectories to be watched for
+    /// hot-reloading, if enabled.
+    #[inline]
+    fn watch_recursive_dir<A: Asset>(&self, _id: &str) {
         #[cfg(feature = "hot-reloading")]
-        for ext in A::EXTENSIONS {
-            let mut path = path.clone();
-            path.set_extension(ext);
-            self.watched.lock().add_file::<A>(path, id.clone());
+        if self.reloader.is_some() {
+            self.watched.lock().add_recursive_dir::<A>(_id.into());
         }
+    }
 
-        let asset: A = load_from_fs(path)?;
+    /// Registers a query to be watched for hot-reloading, if enabled.
+    #[inline]
+    fn watch_query<A: Asset, Q: 'static>(&self, _id: &str, _root: &str, _matcher: Matcher) {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            self.watched.lock().add_query::<A, Q>(_id.into(), _root.into(), _matcher);
+        }
+    }
+
+    /// Stops watching an asset removed from the cache.
+    #[inline]
+    fn unwatch_asset(&self, _key: Key) {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            self.compounds.lock().remove(&_key);
+            self.watched.lock().remove_asset(_key);
+        }
+    }
+
+    /// Evicts assets that do not fit in the memory budget anymore, except
+    /// `keep`, and stops watching them.
+    ///
+    /// Returns the number of evicted assets.
+    fn evict(&self, keep: Option<&Key>) -> usize {
+        let evicted = self.budget.evict(&self.assets, keep);
+        let count = evicted.len();
+        for (key, entry) in evicted {
+            self.unwatch_asset(key);
+            self.retire(entry);
+        }
+        count
+    }
+
+    /// Drops an entry removed from a shared cache, or keeps it until the cache
+    /// is mutably borrowed if an `AssetRef` may still point to it.
+    pub(crate) fn retire(&self, entry: CacheEntry) {
+        if entry.is_borrowed() {
+            let mut retired = self.retired.lock();
+            retired.size += entry.size();
+            retired.assets.push(entry);
+        }
+    }
+
+    /// Drops entries removed from the cache while it was shared.
+    ///
+    /// Taking `&mut self` ensures that they are not borrowed anymore.
+    #[inline]
+    fn reclaim(&mut self) {
+        let retired = self.retired.get_mut();
+        retired.assets.clear();
+        retired.dirs.clear();
+        retired.size = 0;
+    }
+
+    /// Adds an asset to the cache
+    pub(crate) fn add_asset<A: Asset>(&self, id: Box<str>) -> Result<AssetRef<'_, A>, AssetError<A>> {
+        self.watch_file::<A>(&id);
+
+        let (asset, size) = load_sized::<A, S>(&*self.source, &id)?;
+        Ok(self.insert_asset(id, asset, size))
+    }
+
+    /// Inserts a loaded asset in the cache, unless it was inserted meanwhile.
+    ///
+    /// Assets that do not fit in the memory budget anymore are evicted.
+    pub(crate) fn insert_asset<A: Asset>(&self, id: Box<str>, asset: A, size: usize) -> AssetRef<'_, A> {
+        let key = Key::new::<A>(id);
+        let mut cache = self.assets.write(&key);
+
+        // Borrowing the entry means that it has to be retired when removed
+        let asset = {
+            let budget = &self.budget;
+            let entry = cache.entry(key.clone()).or_insert_with(|| {
+                let entry = CacheEntry::new(asset);
+                entry.set_size(size);
+                budget.add(&entry);
+                entry
+            });
+            entry.touch(budget.tick());
+
+            // Safety:
+            // The entry was created with type `A`
+            // The cache entry is garantied to live long enough
+            unsafe { entry.get_ref() }
+        };
+        drop(cache);
+
+        self.evict(Some(&key));
+        asset
+    }
+
+    fn add_compound<C: Compound>(&self, id: Box<str>) -> Result<AssetRef<'_, C>, Box<dyn Error + Send + Sync>> {
+        #[cfg(feature = "hot-reloading")]
+        let asset = if self.reloader.is_some() {
+            let (asset, deps) = compound::record(|| C::load(self, &id));
+            let asset = asset?;
+            self.compounds.lock().insert::<C>(Key::new::<C>(id.clone()), deps);
+            asset
+        } else {
+            C::load(self, &id)?
+        };
+        #[cfg(not(feature = "hot-reloading"))]
+        let asset = C::load(self, &id)?;
+
+        let key = Key::new::<C>(id);
+        let mut cache = self.assets.write(&key);
+        let entry = cache.entry(key).or_insert_with(|| CacheEntry::new(asset));
 
-        let entry = CacheEntry::new(asset);
         // Safety:
-        // We just created the asset with the good type
+        // The entry was created with type `C`
         // The cache entry is garantied to live long enough
-        let asset = unsafe { entry.get_ref() };
+        unsafe { Ok(entry.get_ref()) }
+    }
 
-        let key = Key::new::<A>(id);
-        let mut cache = self.assets.write();
-        cache.insert(key, entry);
+    /// Replaces the value of a compound after it was rebuilt.
+    ///
+    /// If `wait` is `false` and the compound is currently locked, it is left
+    /// unchanged and `false` is returned.
+    #[cfg(feature = "hot-reloading")]
+    pub(crate) fn reload_compound<C: Compound>(&self, id: &str, asset: C, wait: bool) -> bool {
+        let key = AccessKey::new::<C>(id);
+        let cache = self.assets.read(&key);
+        if let Some(entry) = cache.get(&key) {
+            unsafe {
+                if wait {
+                    entry.replace(asset);
+                } else {
+                    return entry.try_write(asset).is_ok();
+                }
+            }
+        }
+        true
+    }
 
-        Ok(asset)
+    fn add_dir<A: Asset>(&self, id: Box<str>) -> Result<DirReader<'_, A, S>, io::Error> {
+        let dir = CachedDir::load::<A, S>(self, &id, false)?;
+        self.watch_dir::<A>(&id);
+        Ok(self.insert_dir(Key::new::<A>(id), dir))
     }
 
-    fn add_dir<A: Asset>(&self, id: Box<str>) -> Result<DirReader<A>, io::Error> {
-        let path = self.path_of(&id);
-        let dir = CachedDir::load::<A>(self, &path, &id)?;
-        let reader = unsafe { dir.read(self) };
+    fn add_dir_recursive<A: Asset>(&self, id: Box<str>) -> Result<DirReader<'_, A, S>, io::Error> {
+        let dir = CachedDir::load::<A, S>(self, &id, true)?;
+        self.watch_recursive_dir::<A>(&id);
+        Ok(self.insert_dir(Key::new::<Recursive<A>>(id), dir))
+    }
 
-        #[cfg(feature = "hot-reloading")]
-        self.watched.lock().add_dir::<A>(path, id.clone());
+    /// Adds a query to the cache, `Q` being the type used in its key.
+    fn add_query<A: Asset, Q: 'static>(&self, id: &str, root: &str, matcher: Matcher) -> Result<DirReader<'_, A, S>, io::Error> {
+        let dir = CachedDir::load_matching::<A, S>(self, root, &*matcher)?;
+        self.watch_query::<A, Q>(id, root, matcher);
+        Ok(self.insert_dir(Key::new::<Q>(id.into()), dir))
+    }
 
-        let key = Key::new::<A>(id);
+    /// Inserts a loaded directory of assets of type `A` in the cache, unless
+    /// it was inserted meanwhile.
+    pub(crate) fn insert_dir<A: Asset>(&self, key: Key, dir: CachedDir) -> DirReader<'_, A, S> {
         let mut dirs = self.dirs.write();
-        dirs.insert(key, dir);
+        let dir = dirs.entry(key).or_insert(dir);
+        unsafe { dir.read(self) }
+    }
 
-        Ok(reader)
+    /// Gets a directory of assets of type `A` from the cache.
+    pub(crate) fn get_cached_dir<A: Asset>(&self, key: &AccessKey) -> Option<DirReader<'_, A, S>> {
+        let dirs = self.dirs.read();
+        dirs.get(key).map(|dir| unsafe { dir.read(self) })
     }
 
     /// Loads an asset.
     ///
-    /// If the asset is not found in the cache, it is loaded from the filesystem.
+    /// If the asset is not found in the cache, it is loaded from the source.
     ///
     /// # Errors
     ///
     /// Errors can occur in several cases :
-    /// - The asset could not be loaded from the filesystem
+    /// - The asset could not be loaded from the source
     /// - Loaded data could not not be converted properly
-    pub fn load<A: Asset>(&self, id: &str) -> Result<AssetRef<A>, AssetError<A>> {
+    pub fn load<A: Asset>(&self, id: &str) -> Result<AssetRef<'_, A>, AssetError<A>> {
         match self.load_cached(id) {
             Some(asset) => Ok(asset),
             None => self.add_asset(id.into()),
         }
     }
 
+    /// Loads an asset and returns an owned handle to it.
+    ///
+    /// Unlike an [`AssetRef`], the returned [`Handle`] does not borrow the
+    /// cache, and stays valid after the asset is removed from the cache.
+    ///
+    /// # Errors
+    ///
+    /// Error cases are the same as [`load`].
+    ///
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`Handle`]: struct.Handle.html
+    /// [`load`]: fn.load.html
+    pub fn load_handle<A: Asset>(&self, id: &str) -> Result<Handle<A>, AssetError<A>> {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Asset(Key::new::<A>(id.into())));
+        }
+
+        // The entry may be evicted as soon as the lock is released, so the
+        // handle has to be created before
+        let key = AccessKey::new::<A>(id);
+        if let Some(entry) = self.assets.read(&key).get(&key) {
+            entry.touch(self.budget.tick());
+            return unsafe { Ok(entry.get_handle()) };
+        }
+
+        self.watch_file::<A>(id);
+        let (asset, size) = load_sized::<A, S>(&*self.source, id)?;
+
+        let mut cache = self.assets.write(&key);
+        let handle = {
+            let budget = &self.budget;
+            let entry = cache.entry(Key::new::<A>(id.into())).or_insert_with(|| {
+                let entry = CacheEntry::new(asset);
+                entry.set_size(size);
+                budget.add(&entry);
+                entry
+            });
+            entry.touch(budget.tick());
+            unsafe { entry.get_handle() }
+        };
+        drop(cache);
+
+        self.evict(None);
+        Ok(handle)
+    }
+
     /// Loads an asset from the cache.
     ///
-    /// This function does not attempt to load the asset from the filesystem if
-    /// it is not found in the cache.
-    pub fn load_cached<A: Asset>(&self, id: &str) -> Option<AssetRef<A>> {
+    /// This function does not attempt to load the asset from the source if it
+    /// is not found in the cache.
+    pub fn load_cached<A: Asset>(&self, id: &str) -> Option<AssetRef<'_, A>> {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Asset(Key::new::<A>(id.into())));
+        }
+
         let key = AccessKey::new::<A>(id);
-        let cache = self.assets.read();
-        cache.get(&key).map(|asset| unsafe { asset.get_ref() })
+        let cache = self.assets.read(&key);
+        cache.get(&key).map(|asset| {
+            asset.touch(self.budget.tick());
+            unsafe { asset.get_ref() }
+        })
     }
 
-    /// Loads an asset given an id, from the filesystem or the cache.
+    /// Loads an asset given an id, from the source or the cache.
     ///
     /// # Panics
     ///
@@ -296,20 +574,21 @@ impl AssetCache {
     ///
     /// [`load`]: fn.load.html
     #[inline]
-    pub fn load_expect<A: Asset>(&self, id: &str) -> AssetRef<A> {
+    pub fn load_expect<A: Asset>(&self, id: &str) -> AssetRef<'_, A> {
         self.load(id).unwrap_or_else(|err| {
             panic!("Failed to load essential asset {:?}: {}", id, err)
         })
     }
 
-    /// Reloads an asset from the filesystem.
+    /// Reloads an asset from the source.
     ///
     /// It does not matter whether the asset has been loaded yet.
     ///
     /// **Note**: this function requires a write lock on the asset, and will block
     /// until one is aquired, ie no read lock can exist at the same time. This
     /// means that you **must not** call this method if you have an `AssetGuard`
-    /// on the same asset, or it may cause a deadlock.
+    /// on the same asset, or it may cause a deadlock. This does not apply with
+    /// feature `arc-swap`.
     ///
     /// # Errors
     ///
@@ -318,11 +597,12 @@ impl AssetCache {
     /// If an error occurs, the asset is left unmodified.
     ///
     /// [`load`]: fn.load.html
-    pub fn force_reload<A: Asset>(&self, id: &str) -> Result<AssetRef<A>, AssetError<A>> {
-        let cache = self.assets.read();
-        if let Some(cached) = cache.get(&AccessKey::new::<A>(id)) {
-            let path = self.path_of(id);
-            let asset = load_from_fs(path)?;
+    pub fn force_reload<A: Asset>(&self, id: &str) -> Result<AssetRef<'_, A>, AssetError<A>> {
+        let key = AccessKey::new::<A>(id);
+        let cache = self.assets.read(&key);
+        if let Some(cached) = cache.get(&key) {
+            let (asset, size) = load_sized::<A, S>(&*self.source, id)?;
+            self.budget.resize(cached, size);
             return unsafe { Ok(cached.write(asset)) };
         }
         drop(cache);
@@ -341,14 +621,217 @@ impl AssetCache {
     ///
     /// An error is returned if the given id does not match a valid readable
     /// directory.
-    pub fn load_dir<A: Asset>(&self, id: &str) -> io::Result<DirReader<A>> {
-        let dirs = self.dirs.read();
-        if let Some(dir) = dirs.get(&AccessKey::new::<A>(id)) {
-            return unsafe { Ok(dir.read(self)) };
+    pub fn load_dir<A: Asset>(&self, id: &str) -> io::Result<DirReader<'_, A, S>> {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Dir(Key::new::<A>(id.into())));
+        }
+
+        match self.get_cached_dir(&AccessKey::new::<A>(id)) {
+            Some(dir) => Ok(dir),
+            None => self.add_dir(id.into()),
+        }
+    }
+
+    /// Load all assets of a given type in a directory and in its
+    /// subdirectories.
+    ///
+    /// This works like [`load_dir`], except that the ids of the assets of
+    /// subdirectories contain the id of their directory, eg
+    /// `monsters.undead.skeleton` for `load_dir_recursive("monsters")`.
+    ///
+    /// With hot-reloading, files added in any subdirectory, including new
+    /// ones, are added to the returned structure.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory. Subdirectories that cannot be read are skipped.
+    ///
+    /// [`load_dir`]: #method.load_dir
+    pub fn load_dir_recursive<A: Asset>(&self, id: &str) -> io::Result<DirReader<'_, A, S>> {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Dir(Key::new::<Recursive<A>>(id.into())));
+        }
+
+        match self.get_cached_dir(&AccessKey::new::<Recursive<A>>(id)) {
+            Some(dir) => Ok(dir),
+            None => self.add_dir_recursive(id.into()),
+        }
+    }
+
+    /// Loads all assets of a given type whose id match a glob pattern.
+    ///
+    /// In the pattern, a `*` matches any part of the name of a file or
+    /// directory, and a `**` component matches any number of directories. For
+    /// example, `ui.*.icon_*` matches `ui.menu.icon_play` but not
+    /// `ui.menu.sub.icon_play`, and `ui.**.icon_*` matches both.
+    ///
+    /// The returned structure works like the one returned by [`load_dir`].
+    /// With hot-reloading, files matching the pattern are added to it when
+    /// they appear, and removed from it when they disappear.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the deepest directory of the pattern that does
+    /// not contain a `*` is not a valid readable directory. For example,
+    /// `ui.*.icon_*` fails if `ui` cannot be read.
+    ///
+    /// [`load_dir`]: #method.load_dir
+    pub fn load_glob<A: Asset>(&self, pattern: &str) -> io::Result<DirReader<'_, A, S>> {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Dir(Key::new::<Glob<A>>(pattern.into())));
+        }
+
+        match self.get_cached_dir(&AccessKey::new::<Glob<A>>(pattern)) {
+            Some(dir) => Ok(dir),
+            None => {
+                let owned_pattern = String::from(pattern);
+                let matcher: Matcher = Arc::new(move |id| dirs::glob_matches(&owned_pattern, id));
+                self.add_query::<A, Glob<A>>(pattern, dirs::glob_root(pattern), matcher)
+            },
+        }
+    }
+
+    /// Loads all assets of a given type in a directory and its
+    /// subdirectories whose id are accepted by `filter`.
+    ///
+    /// The returned structure works like the one returned by
+    /// [`load_dir_recursive`]. With hot-reloading, files accepted by the filter
+    /// are added to it when they appear, and removed from it when they
+    /// disappear.
+    ///
+    /// The returned structure is cached using the given id and the type of the
+    /// filter, which is why it cannot capture variables: if you need a filter
+    /// that depends on runtime values, use [`load_dir_recursive`] and filter
+    /// the ids when iterating.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    ///
+    /// `filter` must be a zero-sized type, ie a closure that does not capture
+    /// variables or the name of a function. This is checked at compile time:
+    ///
+    /// ```compile_fail
+    /// # use assets_manager::{Asset, AssetCache, loader::{LoadFrom, ParseLoader}};
+    /// # struct Sprite(i32);
+    /// # impl From<i32> for Sprite { fn from(n: i32) -> Sprite { Sprite(n) } }
+    /// # impl Asset for Sprite {
+    /// #     const EXTENSION: &'static str = "x";
+    /// #     type Loader = LoadFrom<i32, ParseLoader>;
+    /// # }
+    /// let cache = AssetCache::new("assets")?;
+    ///
+    /// let prefix = String::from("ui.menu");
+    /// let _ = cache.load_filtered::<Sprite, _>("ui", move |id| id.starts_with(&prefix));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use assets_manager::{Asset, AssetCache, loader::{LoadFrom, ParseLoader}};
+    /// # struct Sprite(i32);
+    /// # impl From<i32> for Sprite { fn from(n: i32) -> Sprite { Sprite(n) } }
+    /// # impl Asset for Sprite {
+    /// #     const EXTENSION: &'static str = "x";
+    /// #     type Loader = LoadFrom<i32, ParseLoader>;
+    /// # }
+    /// let cache = AssetCache::new("assets")?;
+    ///
+    /// let icons = cache.load_filtered::<Sprite, _>("ui", |id| id.ends_with("_icon"))?;
+    /// for (id, _) in icons.iter_all() {
+    ///     println!("Found icon {}", id);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`load_dir_recursive`]: #method.load_dir_recursive
+    pub fn load_filtered<A, F>(&self, id: &str, filter: F) -> io::Result<DirReader<'_, A, S>>
+    where
+        A: Asset,
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        // Compile-time assert that the filter does not capture variables
+        let _ = Filtered::<A, F>::ZERO_SIZED_FILTER_REQUIRED;
+
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Dir(Key::new::<Filtered<A, F>>(id.into())));
+        }
+
+        match self.get_cached_dir(&AccessKey::new::<Filtered<A, F>>(id)) {
+            Some(dir) => Ok(dir),
+            None => {
+                let dir_id = String::from(id);
+                let matcher: Matcher = Arc::new(move |id| dirs::is_in_dir(&dir_id, id) && filter(id));
+                self.add_query::<A, Filtered<A, F>>(id, id, matcher)
+            },
+        }
+    }
+
+    /// Loads all assets of a given type in a directory, in parallel.
+    ///
+    /// This is the same as [`load_dir`], except that assets of the directory
+    /// are read and parsed concurrently using *rayon*'s global thread pool.
+    ///
+    /// # Error
+    ///
+    /// An error is returned if the given id does not match a valid readable
+    /// directory.
+    ///
+    /// [`load_dir`]: #method.load_dir
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn load_dir_par<A: Asset>(&self, id: &str) -> io::Result<DirReader<'_, A, S>>
+    where
+        S: Send + Sync,
+    {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Dir(Key::new::<A>(id.into())));
+        }
+
+        match self.get_cached_dir(&AccessKey::new::<A>(id)) {
+            Some(dir) => Ok(dir),
+            None => {
+                let dir = CachedDir::load_par::<A, S>(self, id)?;
+                self.watch_dir::<A>(id);
+                Ok(self.insert_dir(Key::new::<A>(id.into()), dir))
+            },
+        }
+    }
+
+    /// Loads a compound.
+    ///
+    /// If the compound is not found in the cache, it is loaded with
+    /// [`Compound::load`], and the assets it loads are recorded to rebuild it
+    /// when they are reloaded.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the compound is not in the cache and
+    /// [`Compound::load`] fails.
+    ///
+    /// [`Compound::load`]: trait.Compound.html#tymethod.load
+    pub fn load_compound<C: Compound>(&self, id: &str) -> Result<AssetRef<'_, C>, Box<dyn Error + Send + Sync>> {
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            compound::recorded(|| Dependency::Asset(Key::new::<C>(id.into())));
         }
-        drop(dirs);
 
-        self.add_dir(id.into())
+        let key = AccessKey::new::<C>(id);
+        let cache = self.assets.read(&key);
+        if let Some(entry) = cache.get(&key) {
+            return unsafe { Ok(entry.get_ref()) };
+        }
+        drop(cache);
+
+        self.add_compound(id.into())
     }
 
     /// Load an owned version of the asset
@@ -356,8 +839,7 @@ impl AssetCache {
     /// Note that it will not try to fetch it from the cache nor to cache it.
     /// In addition, hot-reloading will not affect the returned value.
     pub fn load_owned<A: Asset>(&self, id: &str) -> Result<A, AssetError<A>> {
-        let path = self.path_of(id);
-        load_from_fs(path)
+        load_from_source(&*self.source, id)
     }
 
     /// Remove an asset from the cache.
@@ -365,76 +847,506 @@ impl AssetCache {
     /// The removed asset matches both the id and the type parameter.
     #[inline]
     pub fn remove<A: Asset>(&mut self, id: &str) {
+        self.reclaim();
+
         let key = AccessKey::new::<A>(id);
-        let cache = self.assets.get_mut();
-        cache.remove(&key);
+        let cache = self.assets.get_mut(&key);
+        if let Some(entry) = cache.remove(&key) {
+            self.budget.remove(&entry);
+        }
     }
 
     /// Take ownership on an asset.
     ///
     /// The corresponding asset is removed from the cache.
+    ///
+    /// If the asset is still shared with a [`Handle`], it is left in the cache
+    /// and `None` is returned.
+    ///
+    /// [`Handle`]: struct.Handle.html
     pub fn take<A: Asset>(&mut self, id: &str) -> Option<A> {
+        self.reclaim();
+
         let key = AccessKey::new::<A>(id);
-        let cache = self.assets.get_mut();
-        cache.remove(&key).map(|entry| unsafe { entry.into_inner() })
+        let cache = self.assets.get_mut(&key);
+        let (key, entry) = cache.remove_entry(&key)?;
+        let size = entry.size();
+
+        match unsafe { entry.into_inner() } {
+            Ok(asset) => {
+                self.budget.remove_size(size);
+                Some(asset)
+            },
+            Err(entry) => {
+                cache.insert(key, entry);
+                None
+            },
+        }
     }
 
     /// Clears the cache.
     #[inline]
     pub fn clear(&mut self) {
-        self.assets.get_mut().clear();
+        self.reclaim();
+        self.assets.shards_mut().for_each(|shard| shard.clear());
         self.dirs.get_mut().clear();
+        self.budget.clear();
 
         #[cfg(feature = "hot-reloading")]
-        self.watched.get_mut().clear();
+        {
+            self.watched.get_mut().clear();
+            self.compounds.get_mut().clear();
+        }
+    }
+
+    /// Removes an asset from a shared cache.
+    ///
+    /// This is the same as [`remove`], but it can be called while the cache is
+    /// shared, for example from another thread. Returns `true` if the asset
+    /// was in the cache.
+    ///
+    /// **If an [`AssetRef`] to the asset was ever created, its memory is not
+    /// freed until the cache is mutably borrowed.** A cache that is never
+    /// mutably borrowed keeps all such assets, so unloading and loading them
+    /// again repeatedly uses more and more memory. This memory is reported by
+    /// [`retired_memory_usage`].
+    ///
+    /// The asset is removed from the cache immediately, so it will be loaded
+    /// again from the source the next time it is requested, and it is not
+    /// hot-reloaded anymore. However, the memory it uses may not be freed
+    /// immediately:
+    /// - If the asset is shared with [`Handle`]s, it is freed when the last
+    ///   one is dropped.
+    /// - If an [`AssetRef`] to the asset was ever created, it may still be
+    ///   alive, so the asset is only freed the next time the cache is mutably
+    ///   borrowed (eg with [`remove`], [`clear`] or [`collect_garbage`]), or
+    ///   when the cache is dropped.
+    ///
+    /// Assets accessed only with [`load_handle`] are therefore freed as soon
+    /// as they are not used anymore.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use assets_manager::{Asset, AssetCache, loader::{LoadFrom, ParseLoader}};
+    /// # struct X(i32);
+    /// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+    /// # impl Asset for X {
+    /// #     const EXTENSION: &'static str = "x";
+    /// #     type Loader = LoadFrom<i32, ParseLoader>;
+    /// # }
+    /// use std::{sync::Arc, thread};
+    ///
+    /// let cache = Arc::new(AssetCache::new("assets")?);
+    /// let handle = cache.load_handle::<X>("test.b")?;
+    ///
+    /// let cache_clone = cache.clone();
+    /// thread::spawn(move || {
+    ///     assert!(cache_clone.unload::<X>("test.b"));
+    /// }).join().unwrap();
+    ///
+    /// assert!(cache.load_cached::<X>("test.b").is_none());
+    /// assert_eq!(handle.read().0, -7);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`remove`]: #method.remove
+    /// [`clear`]: #method.clear
+    /// [`collect_garbage`]: #method.collect_garbage
+    /// [`load_handle`]: #method.load_handle
+    /// [`retired_memory_usage`]: #method.retired_memory_usage
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`Handle`]: struct.Handle.html
+    pub fn unload<A: Asset>(&self, id: &str) -> bool {
+        let key = AccessKey::new::<A>(id);
+        let removed = self.assets.write(&key).remove_entry(&key);
+
+        match removed {
+            Some((key, entry)) => {
+                self.budget.remove(&entry);
+                self.unwatch_asset(key);
+                self.retire(entry);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Removes all assets and directories from a shared cache.
+    ///
+    /// This is the same as [`clear`], but it can be called while the cache is
+    /// shared.
+    ///
+    /// **Assets that were ever given as an [`AssetRef`] and all directories
+    /// are not freed until the cache is mutably borrowed**, as `AssetRef`s and
+    /// [`DirReader`]s may still point to them. See [`unload`] for more
+    /// details.
+    ///
+    /// [`clear`]: #method.clear
+    /// [`unload`]: #method.unload
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`DirReader`]: struct.DirReader.html
+    pub fn unload_all(&self) {
+        let shards: Vec<_> = self.assets.shards()
+            .map(|shard| mem::replace(&mut *shard.write(), HashMap::new()))
+            .collect();
+        let mut dirs = mem::replace(&mut *self.dirs.write(), HashMap::new());
+        self.budget.clear();
+
+        #[cfg(feature = "hot-reloading")]
+        if self.reloader.is_some() {
+            self.watched.lock().clear();
+            self.compounds.lock().clear();
+        }
+
+        let mut retired = self.retired.lock();
+        for mut assets in shards {
+            for (_, entry) in assets.drain() {
+                if entry.is_borrowed() {
+                    retired.size += entry.size();
+                    retired.assets.push(entry);
+                }
+            }
+        }
+        retired.dirs.extend(dirs.drain().map(|(_, dir)| dir));
+    }
+
+    /// Removes from the cache all assets that are not shared with a [`Handle`],
+    /// and all directories.
+    ///
+    /// As no [`AssetRef`] or [`DirReader`] can exist while the cache is
+    /// mutably borrowed, this is a convenient way to free the memory used by
+    /// assets that are not used anymore, for example when a level is unloaded:
+    /// assets kept alive with [`Handle`]s are left in the cache, and
+    /// everything else is loaded again when needed.
+    ///
+    /// Removed assets and directories are not hot-reloaded anymore.
+    ///
+    /// Returns the number of removed assets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use assets_manager::{Asset, AssetCache, loader::{LoadFrom, ParseLoader}};
+    /// # struct X(i32);
+    /// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+    /// # impl Asset for X {
+    /// #     const EXTENSION: &'static str = "x";
+    /// #     type Loader = LoadFrom<i32, ParseLoader>;
+    /// # }
+    /// let mut cache = AssetCache::new("assets")?;
+    ///
+    /// let kept = cache.load_handle::<X>("test.b")?;
+    /// cache.load::<X>("test.cache")?;
+    ///
+    /// assert_eq!(cache.collect_garbage(), 1);
+    /// assert!(cache.load_cached::<X>("test.b").is_some());
+    /// assert!(cache.load_cached::<X>("test.cache").is_none());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`DirReader`]: struct.DirReader.html
+    /// [`Handle`]: struct.Handle.html
+    pub fn collect_garbage(&mut self) -> usize {
+        self.reclaim();
+        let budget = &self.budget;
+        let mut removed = Vec::new();
+
+        for shard in self.assets.shards_mut() {
+            shard.retain(|key, entry| {
+                entry.release();
+                let keep = !entry.is_evictable();
+                if !keep {
+                    budget.remove(entry);
+                    removed.push(key.clone());
+                }
+                keep
+            });
+        }
+        let count = removed.len();
+
+        let dirs = self.dirs.get_mut();
+        #[cfg(feature = "hot-reloading")]
+        {
+            let watched = self.watched.get_mut();
+            let compounds = self.compounds.get_mut();
+
+            for key in removed {
+                compounds.remove(&key);
+                watched.remove_asset(key);
+            }
+            for (key, _) in dirs.drain() {
+                watched.remove_dir(key);
+            }
+        }
+        dirs.clear();
+
+        count
+    }
+
+    /// Sets the maximum amount of memory that assets of the cache should use,
+    /// in bytes. `None` means no limit, which is the default.
+    ///
+    /// When the budget is exceeded, assets of the cache are evicted in least
+    /// recently used order. Accessing an asset with [`load`], [`load_cached`]
+    /// or [`load_handle`] marks it as used. Assets currently shared with a
+    /// [`Handle`] are never evicted, and an asset is not evicted when it is
+    /// being loaded.
+    ///
+    /// The size of an asset is estimated with the size of the file it was
+    /// loaded from. Compounds are not counted, but the assets they load are.
+    ///
+    /// Evicted assets are removed from the cache the same way as with
+    /// [`unload`]: they are loaded again from the source the next time they
+    /// are requested, and they are not hot-reloaded anymore. If an
+    /// [`AssetRef`] to an evicted asset was ever created, the asset stays
+    /// valid and its memory is only freed the next time the cache is mutably
+    /// borrowed (eg with [`trim`]), see [`retired_memory_usage`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use assets_manager::{Asset, AssetCache, loader::{LoadFrom, ParseLoader}};
+    /// # struct X(i32);
+    /// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+    /// # impl Asset for X {
+    /// #     const EXTENSION: &'static str = "x";
+    /// #     type Loader = LoadFrom<i32, ParseLoader>;
+    /// # }
+    /// let cache = AssetCache::new("assets")?;
+    /// cache.set_memory_budget(Some(3));
+    ///
+    /// // Both files contain 2 bytes, so they cannot fit in the cache together
+    /// let handle = cache.load_handle::<X>("test.b")?;
+    /// drop(handle);
+    /// cache.load_handle::<X>("test.cache")?;
+    ///
+    /// assert!(cache.load_cached::<X>("test.b").is_none());
+    /// assert!(cache.memory_usage() <= 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [`load`]: #method.load
+    /// [`load_cached`]: #method.load_cached
+    /// [`load_handle`]: #method.load_handle
+    /// [`trim`]: #method.trim
+    /// [`unload`]: #method.unload
+    /// [`retired_memory_usage`]: #method.retired_memory_usage
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`Handle`]: struct.Handle.html
+    pub fn set_memory_budget(&self, budget: Option<usize>) {
+        self.budget.set_limit(budget);
+        self.evict(None);
+    }
+
+    /// Returns the memory budget of the cache, in bytes.
+    ///
+    /// See [`set_memory_budget`] for more details.
+    ///
+    /// [`set_memory_budget`]: #method.set_memory_budget
+    #[inline]
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.budget.limit()
+    }
+
+    /// Returns the estimated amount of memory used by assets of the cache, in
+    /// bytes.
+    ///
+    /// See [`set_memory_budget`] for more details.
+    ///
+    /// [`set_memory_budget`]: #method.set_memory_budget
+    #[inline]
+    pub fn memory_usage(&self) -> usize {
+        self.budget.used()
+    }
+
+    /// Returns the estimated amount of memory used by assets that were
+    /// unloaded or evicted from the shared cache but not freed yet, in bytes.
+    ///
+    /// This memory is not counted by [`memory_usage`]. It is freed the next
+    /// time the cache is mutably borrowed, see [`unload`] for more details.
+    ///
+    /// [`memory_usage`]: #method.memory_usage
+    /// [`unload`]: #method.unload
+    pub fn retired_memory_usage(&self) -> usize {
+        self.retired.lock().size
+    }
+
+    /// Evicts least recently used assets until the memory usage fits in the
+    /// budget.
+    ///
+    /// As no [`AssetRef`] can exist while the cache is mutably borrowed, the
+    /// memory of evicted assets is freed immediately, along with the memory
+    /// of assets that were previously unloaded or evicted while the cache was
+    /// shared. Assets shared with a [`Handle`] are never evicted.
+    ///
+    /// Returns the number of evicted assets.
+    ///
+    /// [`AssetRef`]: struct.AssetRef.html
+    /// [`Handle`]: struct.Handle.html
+    pub fn trim(&mut self) -> usize {
+        self.reclaim();
+        for shard in self.assets.shards_mut() {
+            shard.values_mut().for_each(CacheEntry::release);
+        }
+        self.evict(None)
     }
 
     /// Reloads changed assets.
     ///
     /// This function is typically called within a loop.
     ///
-    /// If an error occurs while reloading an asset, a warning will be logged
-    /// and the asset will be left unchanged.
+    /// Compounds that depend on reloaded assets are then rebuilt, see
+    /// [`Compound`] for more details.
+    ///
+    /// Returns a [`ReloadReport`] describing what happened:
+    /// - `reloaded` lists the assets and compounds that were reloaded.
+    /// - `failed` lists the assets and compounds that could not be reloaded,
+    ///   with the error message. These are left unchanged.
+    /// - `dirs` lists the changes of cached directories.
+    ///
+    /// Errors are only logged at the `debug` level, so they should be taken
+    /// from the report.
     ///
     /// This function blocks the current thread until all changed assets are
     /// reloaded, but it does not perform any I/O. However, it needs to lock
     /// some assets for writing, so you **must not** have any [`AssetGuard`]
     /// from the given `AssetCache`, or you might experience deadlocks. You are
     /// free to keep [`AssetRef`]s, though. The same restriction applies to
-    /// [`ReadDir`] and [`ReadAllDir`].
+    /// [`ReadDir`] and [`ReadAllDir`]. With feature `arc-swap`, you can keep
+    /// [`AssetGuard`]s, but not [`ReadDir`]s and [`ReadAllDir`]s.
+    ///
+    /// If the cache's source does not support hot-reloading, this function
+    /// does nothing.
     ///
     /// [`AssetGuard`]: struct.AssetGuard.html
     /// [`AssetRef`]: struct.AssetRef.html
     /// [`ReadDir`]: struct.ReadDir.html
     /// [`ReadAllDir`]: struct.ReadAllDir.html
+    /// [`Compound`]: trait.Compound.html
+    /// [`ReloadReport`]: hot_reloading/struct.ReloadReport.html
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn hot_reload(&self) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        if let Some(reloader) = &self.reloader {
+            let changed = reloader.reload(self, &mut report);
+            self.rebuild_compounds(changed, &mut report, true);
+        }
+        report
+    }
+
+    /// Returns a channel that receives a [`Change`] each time the given asset
+    /// is reloaded by [`hot_reload`].
+    ///
+    /// This works for both assets and [`Compound`]s, and is a cheaper
+    /// alternative to calling [`AssetRef::reloaded`] on many assets every
+    /// frame. The asset does not need to be loaded yet, and it stays watched
+    /// after being removed from the cache and loaded again.
+    ///
+    /// Changes are sent during calls to [`hot_reload`], so it is fine to
+    /// read the channel from the same thread. Dropping the receiver ends the
+    /// subscription.
+    ///
+    /// If the cache's source does not support hot-reloading, the channel
+    /// never receives anything.
+    ///
+    /// [`Change`]: hot_reloading/enum.Change.html
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`Compound`]: trait.Compound.html
+    /// [`AssetRef::reloaded`]: struct.AssetRef.html#method.reloaded
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn subscribe<A: 'static>(&self, id: &str) -> mpsc::Receiver<Change> {
+        self.subscribers.lock().subscribe_asset(Key::new::<A>(id.into()))
+    }
+
+    /// Returns a channel that receives a [`Change`] each time an asset is
+    /// added to or removed from the directory returned by [`load_dir`] with
+    /// the same arguments.
+    ///
+    /// Changes of the assets of the directory are not sent to this channel,
+    /// use [`subscribe`] for them. To follow recursive directories and queries
+    /// such as [`load_glob`], use [`subscribe_all`] and filter changes by
+    /// [`DirChange::dir`].
+    ///
+    /// [`Change`]: hot_reloading/enum.Change.html
+    /// [`load_dir`]: #method.load_dir
+    /// [`subscribe`]: #method.subscribe
+    /// [`load_glob`]: #method.load_glob
+    /// [`subscribe_all`]: #method.subscribe_all
+    /// [`DirChange::dir`]: hot_reloading/struct.DirChange.html#structfield.dir
     #[cfg(feature = "hot-reloading")]
     #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
-    pub fn hot_reload(&self) {
-        self.reloader.lock().reload(self);
+    pub fn subscribe_dir<A: Asset>(&self, id: &str) -> mpsc::Receiver<Change> {
+        self.subscribers.lock().subscribe_dir(Key::new::<A>(id.into()))
+    }
+
+    /// Returns a channel that receives every [`Change`] made to the cache by
+    /// [`hot_reload`].
+    ///
+    /// [`Change`]: hot_reloading/enum.Change.html
+    /// [`hot_reload`]: #method.hot_reload
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn subscribe_all(&self) -> mpsc::Receiver<Change> {
+        self.subscribers.lock().subscribe_all()
     }
 }
 
-impl fmt::Debug for AssetCache {
+impl<S> fmt::Debug for AssetCache<S>
+where
+    S: fmt::Debug,
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AssetCache")
-            .field("path", &self.path)
-            .field("assets", &self.assets.read())
+            .field("source", &self.source)
+            .field("assets", &self.assets)
             .field("dirs", &self.dirs.read())
             .finish()
     }
 }
 
-fn load_from_fs<A: Asset>(mut path: PathBuf) -> Result<A, AssetError<A>> {
+/// Entries removed from a shared cache, which may still be borrowed.
+#[derive(Default)]
+pub(crate) struct Retired {
+    pub assets: Vec<CacheEntry>,
+    pub dirs: Vec<CachedDir>,
+    /// The estimated size of retired assets
+    pub size: usize,
+}
+
+pub(crate) fn load_from_source<A, S>(source: &S, id: &str) -> Result<A, AssetError<A>>
+where
+    A: Asset,
+    S: Source + ?Sized,
+{
+    load_sized(source, id).map(|(asset, _)| asset)
+}
+
+/// Loads an asset from a source, and returns it with the size of the file it
+/// was loaded from.
+pub(crate) fn load_sized<A, S>(source: &S, id: &str) -> Result<(A, usize), AssetError<A>>
+where
+    A: Asset,
+    S: Source + ?Sized,
+{
     // Compile-time assert that the asset type has at least one extension
     let _ = <A as Asset>::_AT_LEAST_ONE_EXTENSION_REQUIRED;
 
     let mut err = None;
 
     for ext in A::EXTENSIONS {
-        path.set_extension(ext);
-        let content = fs::read(&path).map(Into::into);
+        let result = match source.read_asset(id, ext, A::EXTENSIONS) {
+            Ok(content) => content.with_cow(|content| {
+                let size = content.len();
+                A::Loader::load(Ok(content), ext).map(|asset| (asset, size))
+            }),
+            Err(err) => A::Loader::load(Err(err), ext).map(|asset| (asset, 0)),
+        };
 
-        match A::Loader::load(content, ext) {
+        match result {
             Err(e) => err = Some(e),
             asset => return asset,
         }
@@ -448,14 +1360,16 @@ fn load_from_fs<A: Asset>(mut path: PathBuf) -> Result<A, AssetError<A>> {
 enum ErrorKind {
     Io(io::Error),
     #[cfg(feature = "hot-reloading")]
-    Notify(notify::Error),
+    Watch(Box<dyn Error + Send + Sync>),
 }
 
 /// An error which occurs when creating a cache.
 ///
-/// This error can be returned by [`AssetCache::new`].
+/// This error can be returned by [`AssetCache::new`] and
+/// [`AssetCache::with_source`].
 ///
 /// [`AssetCache::new`]: struct.AssetCache.html#method.new
+/// [`AssetCache::with_source`]: struct.AssetCache.html#method.with_source
 pub struct CacheError(ErrorKind);
 
 impl From<ErrorKind> for CacheError {
@@ -471,7 +1385,7 @@ impl fmt::Debug for CacheError {
         match &self.0 {
             ErrorKind::Io(err) => debug.field(err),
             #[cfg(feature = "hot-reloading")]
-            ErrorKind::Notify(err) => debug.field(err),
+            ErrorKind::Watch(err) => debug.field(err),
         }.finish()
     }
 }
@@ -481,7 +1395,7 @@ impl fmt::Display for CacheError {
         match &self.0 {
             ErrorKind::Io(err) => err.fmt(f),
             #[cfg(feature = "hot-reloading")]
-            ErrorKind::Notify(err) => err.fmt(f),
+            ErrorKind::Watch(err) => err.fmt(f),
         }
     }
 }
@@ -491,7 +1405,7 @@ impl Error for CacheError {
         match &self.0 {
             ErrorKind::Io(err) => Some(err),
             #[cfg(feature = "hot-reloading")]
-            ErrorKind::Notify(err) => Some(err),
+            ErrorKind::Watch(err) => Some(&**err),
         }
     }
 }
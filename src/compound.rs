@@ -0,0 +1,308 @@
+//! Assets made of other assets
+
+use crate::{AssetCache, source::Source};
+
+use std::error::Error;
+
+#[cfg(feature = "hot-reloading")]
+pub(crate) use self::deps::{Dependency, Graph, record, recorded};
+
+
+/// An asset made of other assets.
+///
+/// Unlike an [`Asset`], a `Compound` is not loaded from the bytes of a single
+/// file, but with an [`AssetCache`], which enables it to load other assets,
+/// directories or compounds.
+///
+/// Compounds are loaded with [`AssetCache::load_compound`]. With
+/// hot-reloading, the assets loaded by a compound are recorded as its
+/// dependencies, so the compound is rebuilt each time one of them is reloaded.
+/// This works recursively, so a compound is always rebuilt after the compounds
+/// it depends on.
+///
+/// Note that reading files directly from the [`Source`] of the cache does not
+/// record dependencies.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, Compound, source::Source};
+/// # use assets_manager::{Asset, loader::{LoadFrom, ParseLoader}};
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+/// # impl Asset for X {
+/// #     const EXTENSION: &'static str = "x";
+/// #     type Loader = LoadFrom<i32, ParseLoader>;
+/// # }
+///
+/// // The sum of the values in a directory
+/// struct Sum(i32);
+///
+/// impl Compound for Sum {
+///     fn load<S: Source>(cache: &AssetCache<S>, id: &str) -> Result<Sum, Box<dyn std::error::Error + Send + Sync>> {
+///         let sum = cache.load_dir::<X>(id)?.iter().map(|x| x.read().0).sum();
+///         Ok(Sum(sum))
+///     }
+/// }
+///
+/// let cache = AssetCache::new("assets")?;
+/// let sum = cache.load_compound::<Sum>("test")?;
+/// assert_eq!(sum.read().0, 35);
+/// # Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+/// ```
+///
+/// [`Asset`]: trait.Asset.html
+/// [`AssetCache`]: struct.AssetCache.html
+/// [`AssetCache::load_compound`]: struct.AssetCache.html#method.load_compound
+/// [`Source`]: source/trait.Source.html
+pub trait Compound: Sized + Send + Sync + 'static {
+    /// Loads the compound from the cache.
+    fn load<S: Source>(cache: &AssetCache<S>, id: &str) -> Result<Self, Box<dyn Error + Send + Sync>>;
+}
+
+
+#[cfg(feature = "hot-reloading")]
+mod deps {
+    use crate::{
+        AssetCache,
+        cache::Key,
+        hot_reloading::{Change, ReloadError, ReloadReport, Reloaded},
+        source::Source,
+        utils::HashMap,
+    };
+
+    use std::{cell::RefCell, collections::HashSet};
+
+    use super::Compound;
+
+    /// Something a compound can depend on.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub(crate) enum Dependency {
+        Asset(Key),
+        Dir(Key),
+    }
+
+    thread_local! {
+        // `const` initializers are not supported by our MSRV
+        #[allow(clippy::missing_const_for_thread_local)]
+        /// Dependencies of the compound being loaded on this thread, if any.
+        static RECORDING: RefCell<Option<HashSet<Dependency>>> = RefCell::new(None);
+    }
+
+    /// Restores the previous recording when dropped, even on panic.
+    struct Restore(Option<HashSet<Dependency>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            RECORDING.with(|rec| *rec.borrow_mut() = previous);
+        }
+    }
+
+    /// Calls `f`, returning the dependencies recorded meanwhile.
+    pub(crate) fn record<T>(f: impl FnOnce() -> T) -> (T, HashSet<Dependency>) {
+        let previous = RECORDING.with(|rec| rec.borrow_mut().replace(HashSet::new()));
+        let restore = Restore(previous);
+
+        let result = f();
+
+        let deps = RECORDING.with(|rec| rec.borrow_mut().take());
+        drop(restore);
+        (result, deps.unwrap_or_default())
+    }
+
+    /// Records a dependency of the compound being loaded, if any.
+    ///
+    /// The dependency is only built if a compound is being loaded, as this is
+    /// called on each cache hit.
+    pub(crate) fn recorded(dep: impl FnOnce() -> Dependency) {
+        RECORDING.with(|rec| {
+            if let Some(deps) = &mut *rec.borrow_mut() {
+                deps.insert(dep());
+            }
+        });
+    }
+
+
+    /// The result of rebuilding a compound.
+    enum Rebuilt {
+        Done(HashSet<Dependency>),
+        Failed,
+        /// The compound is currently locked and has to be rebuilt later.
+        Locked,
+    }
+
+    type ReloadFn<S> = fn(&AssetCache<S>, &Key, &mut ReloadReport, bool) -> Rebuilt;
+
+    fn reload<C: Compound, S: Source>(cache: &AssetCache<S>, key: &Key, report: &mut ReloadReport, wait: bool) -> Rebuilt {
+        let id = key.id();
+        let (result, deps) = record(|| C::load(cache, id));
+        let type_name = std::any::type_name::<C>();
+
+        match result {
+            Ok(asset) => {
+                if !cache.reload_compound(id, asset, wait) {
+                    return Rebuilt::Locked;
+                }
+
+                let reloaded = Reloaded { id: id.into(), type_name };
+                let mut subscribers = cache.subscribers.lock();
+                if !subscribers.is_empty() {
+                    subscribers.notify_asset(key, Change::Asset(reloaded.clone()));
+                }
+                report.reloaded.push(reloaded);
+                Rebuilt::Done(deps)
+            },
+            Err(err) => {
+                log::debug!("Error rebuilding {:?}: {}", id, err);
+                report.failed.push(ReloadError {
+                    id: id.into(),
+                    ext: None,
+                    type_name,
+                    message: err.to_string(),
+                });
+                Rebuilt::Failed
+            },
+        }
+    }
+
+    /// The dependency graph of compounds.
+    pub(crate) struct Graph<S> {
+        compounds: HashMap<Key, (ReloadFn<S>, HashSet<Dependency>)>,
+        /// Compounds that could not be written because they were locked.
+        stale: HashSet<Key>,
+    }
+
+    impl<S: Source> Graph<S> {
+        pub fn new() -> Self {
+            Graph {
+                compounds: HashMap::new(),
+                stale: HashSet::new(),
+            }
+        }
+
+        pub fn insert<C: Compound>(&mut self, key: Key, deps: HashSet<Dependency>) {
+            self.compounds.insert(key, (reload::<C, S>, deps));
+        }
+
+        pub fn clear(&mut self) {
+            self.compounds.clear();
+            self.stale.clear();
+        }
+
+        pub fn remove(&mut self, key: &Key) {
+            self.compounds.remove(key);
+            self.stale.remove(key);
+        }
+
+        /// Returns compounds that have to be rebuilt when the given
+        /// dependencies change, in the order in which they should be rebuilt.
+        fn to_rebuild(&self, changed: &HashSet<Dependency>) -> Vec<(Key, ReloadFn<S>)> {
+            // Find all compounds affected by the changes
+            let mut affected: HashSet<_> = self.stale.iter()
+                .filter(|key| self.compounds.contains_key(*key))
+                .cloned()
+                .collect();
+            loop {
+                let len = affected.len();
+                for (key, (_, deps)) in self.compounds.iter() {
+                    if affected.contains(key) {
+                        continue;
+                    }
+                    let is_affected = deps.iter().any(|dep| {
+                        changed.contains(dep) || match dep {
+                            Dependency::Asset(key) => affected.contains(key),
+                            Dependency::Dir(_) => false,
+                        }
+                    });
+                    if is_affected {
+                        affected.insert(key.clone());
+                    }
+                }
+                if affected.len() == len {
+                    break;
+                }
+            }
+
+            // Sort them so that a compound comes after its dependencies
+            let mut sorted = Vec::with_capacity(affected.len());
+            while !affected.is_empty() {
+                let ready: Vec<_> = affected.iter().filter(|key| {
+                    let (_, deps) = &self.compounds[*key];
+                    !deps.iter().any(|dep| match dep {
+                        Dependency::Asset(dep) => dep != *key && affected.contains(dep),
+                        Dependency::Dir(_) => false,
+                    })
+                }).cloned().collect();
+
+                // There should not be any cycle, but we do not want to loop
+                // forever if there is one
+                let ready = if ready.is_empty() {
+                    affected.iter().cloned().collect()
+                } else {
+                    ready
+                };
+
+                for key in ready {
+                    affected.remove(&key);
+                    let reload = self.compounds[&key].0;
+                    sorted.push((key, reload));
+                }
+            }
+
+            sorted
+        }
+    }
+
+    impl<S: Source> AssetCache<S> {
+        /// Rebuilds the compounds that depend on the changed dependencies.
+        ///
+        /// If `wait` is `false`, compounds that are currently locked are
+        /// rebuilt on a later call instead.
+        pub(crate) fn rebuild_compounds(&self, mut changed: HashSet<Dependency>, report: &mut ReloadReport, wait: bool) {
+            let to_rebuild = {
+                let graph = self.compounds.lock();
+                if changed.is_empty() && graph.stale.is_empty() {
+                    return;
+                }
+                graph.to_rebuild(&changed)
+            };
+
+            for (key, reload) in to_rebuild {
+                // Only rebuild a compound if one of its dependencies actually
+                // changed, which is not the case if rebuilding one of them
+                // failed
+                let is_changed = {
+                    let graph = self.compounds.lock();
+                    graph.stale.contains(&key) || match graph.compounds.get(&key) {
+                        Some((_, deps)) => deps.iter().any(|dep| changed.contains(dep)),
+                        None => false,
+                    }
+                };
+                if !is_changed {
+                    continue;
+                }
+
+                log::info!("Rebuilding {:?}", key.id());
+
+                match reload(self, &key, report, wait) {
+                    Rebuilt::Done(deps) => {
+                        let mut graph = self.compounds.lock();
+                        graph.stale.remove(&key);
+                        if let Some((_, old)) = graph.compounds.get_mut(&key) {
+                            *old = deps;
+                        }
+                        drop(graph);
+                        changed.insert(Dependency::Asset(key));
+                    },
+                    Rebuilt::Failed => {
+                        self.compounds.lock().stale.remove(&key);
+                    },
+                    Rebuilt::Locked => {
+                        self.compounds.lock().stale.insert(key);
+                    },
+                }
+            }
+        }
+    }
+}
@@ -3,16 +3,20 @@ use crate::{
     AssetCache,
     AssetError,
     AssetRef,
+    source::{DirEntry, FileSystem, Source},
     utils::{RwLock, RwLockReadGuard},
 };
 
+#[cfg(feature = "hot-reloading")]
+use crate::utils::RwLockWriteGuard;
+
 use std::{
     iter::FusedIterator,
     io,
     fmt,
-    fs,
     marker::PhantomData,
     path::Path,
+    sync::Arc,
 };
 
 #[inline]
@@ -23,14 +27,6 @@ pub(crate) fn extension_of(path: &Path) -> Option<&str> {
     }
 }
 
-#[inline]
-fn has_extension(path: &Path, ext: &[&str]) -> bool {
-    match extension_of(path) {
-        Some(file_ext) => ext.contains(&file_ext),
-        None => false,
-    }
-}
-
 #[inline]
 pub(crate) fn id_push(id: &mut String, name: &str) {
     if !id.is_empty() {
@@ -39,6 +35,23 @@ pub(crate) fn id_push(id: &mut String, name: &str) {
     id.push_str(name);
 }
 
+/// Returns the id and the extension of a file given its path relative to the
+/// root of a source.
+#[cfg(any(feature = "hot-reloading", feature = "zip"))]
+pub(crate) fn id_of_relative_path(path: &Path) -> Option<(String, &str)> {
+    let ext = extension_of(path)?;
+
+    let mut id = String::new();
+    if let Some(parent) = path.parent() {
+        for component in parent.iter() {
+            id_push(&mut id, component.to_str()?);
+        }
+    }
+    id_push(&mut id, path.file_stem()?.to_str()?);
+
+    Some((id, ext))
+}
+
 struct StringList {
     list: RwLock<Vec<Box<str>>>,
 }
@@ -115,65 +128,178 @@ pub(crate) struct CachedDir {
     assets: Box<StringList>,
 }
 
-impl CachedDir {
-    pub fn load<A: Asset>(cache: &AssetCache, path: &Path, id: &str) -> Result<Self, io::Error> {
-        let entries = fs::read_dir(path)?;
+/// Marker type used to identify recursive directories in the cache.
+pub(crate) struct Recursive<A>(PhantomData<A>);
+
+/// Marker type used to identify glob queries in the cache.
+pub(crate) struct Glob<A>(PhantomData<A>);
+
+/// Marker type used to identify filtered directories in the cache.
+pub(crate) struct Filtered<A, F>(PhantomData<(A, F)>);
+
+impl<A, F> Filtered<A, F> {
+    /// Compile-time assertion that the filter is zero-sized, as its type is
+    /// the only thing that identifies it.
+    pub const ZERO_SIZED_FILTER_REQUIRED: usize = [0][std::mem::size_of::<F>()];
+}
+
+/// A function that tells whether an id is part of a query.
+pub(crate) type Matcher = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Returns `true` if `id` is in the directory `dir` or in one of its
+/// subdirectories.
+pub(crate) fn is_in_dir(dir: &str, id: &str) -> bool {
+    dir.is_empty() || (id.starts_with(dir) && id[dir.len()..].starts_with('.'))
+}
 
-        let mut loaded = Vec::new();
+/// Returns the longest directory that contains all ids matched by a glob
+/// pattern.
+pub(crate) fn glob_root(pattern: &str) -> &str {
+    let mut end = 0;
 
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
+    for (pos, _) in pattern.match_indices('.') {
+        if pattern[end..pos].contains('*') {
+            break;
+        }
+        end = pos;
+    }
+
+    &pattern[..end]
+}
 
-                if !has_extension(&path, A::EXTENSIONS) {
-                    continue;
-                }
+/// Returns `true` if `id` matches the glob `pattern`.
+///
+/// A `*` matches any part of a name, and a `**` name matches any number of
+/// directories.
+pub(crate) fn glob_matches(pattern: &str, id: &str) -> bool {
+    let pattern: Vec<_> = pattern.split('.').collect();
+    let id: Vec<_> = id.split('.').collect();
+    match_names(&pattern, &id)
+}
+
+fn match_names(pattern: &[&str], id: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => id.is_empty(),
+        Some((&"**", rest)) => (0..=id.len()).any(|i| match_names(rest, &id[i..])),
+        Some((name_pattern, rest)) => match id.split_first() {
+            Some((name, id)) => match_name(name_pattern, name) && match_names(rest, id),
+            None => false,
+        },
+    }
+}
 
-                let name = match path.file_stem().and_then(|n| n.to_str()) {
-                    Some(name) => name,
-                    None => continue,
-                };
+/// Returns `true` if `name` matches `pattern`, in which a `*` matches any
+/// sequence of characters.
+pub(crate) fn match_name(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(pos) => {
+            let (prefix, rest) = (&pattern[..pos], &pattern[pos+1..]);
+            name.starts_with(prefix) && (prefix.len()..=name.len())
+                .any(|i| name.is_char_boundary(i) && match_name(rest, &name[i..]))
+        },
+    }
+}
 
-                if path.is_file() {
-                    let mut this_id = id.to_owned();
-                    id_push(&mut this_id, name);
+/// Returns the ids of the assets of type `A` in a directory, and in its
+/// subdirectories if `recursive` is `true`.
+pub(crate) fn list_dir<A: Asset, S: Source + ?Sized>(source: &S, id: &str, recursive: bool) -> io::Result<Vec<Box<str>>> {
+    let mut ids: Vec<Box<str>> = Vec::new();
+    let mut dirs = Vec::new();
 
-                    let _ = cache.load::<A>(&this_id);
-                    loaded.push(this_id.into());
-                }
+    source.read_dir(id, &mut |entry| match entry {
+        DirEntry::File(id, ext) => {
+            if A::EXTENSIONS.contains(&ext) {
+                ids.push(id.into());
             }
+        },
+        DirEntry::Directory(id) => {
+            if recursive {
+                dirs.push(Box::<str>::from(id));
+            }
+        },
+    })?;
+
+    // An asset with several extensions may be listed several times
+    ids.sort_unstable();
+    ids.dedup();
+
+    for dir in dirs {
+        // A subdirectory may have been removed meanwhile
+        if let Ok(sub_ids) = list_dir::<A, S>(source, &dir, true) {
+            ids.extend(sub_ids);
+        }
+    }
+
+    Ok(ids)
+}
+
+impl CachedDir {
+    pub fn load<A: Asset, S: Source>(cache: &AssetCache<S>, id: &str, recursive: bool) -> Result<Self, io::Error> {
+        let ids = list_dir::<A, S>(cache.source(), id, recursive)?;
+
+        for id in &ids {
+            let _ = cache.load::<A>(id);
         }
 
-        Ok(Self {
-            assets: Box::new(loaded.into()),
-        })
+        Ok(Self::new(ids))
     }
 
-    #[cfg(feature = "hot-reloading")]
-    #[inline]
-    pub fn contains(&self, id: &str) -> bool {
-        self.assets.into_iter().any(|s| s == id)
+    /// Loads the assets of a directory and its subdirectories whose id are
+    /// accepted by `matches`.
+    pub fn load_matching<A: Asset, S: Source>(cache: &AssetCache<S>, id: &str, matches: &dyn Fn(&str) -> bool) -> Result<Self, io::Error> {
+        let mut ids = list_dir::<A, S>(cache.source(), id, true)?;
+        ids.retain(|id| matches(id));
+
+        for id in &ids {
+            let _ = cache.load::<A>(id);
+        }
+
+        Ok(Self::new(ids))
     }
 
-    #[cfg(feature = "hot-reloading")]
-    #[inline]
-    pub fn add(&self, id: Box<str>) {
-        let mut list = self.assets.list.write();
-        list.push(id);
+    /// Same as `load`, but loads assets in parallel.
+    #[cfg(feature = "rayon")]
+    pub fn load_par<A: Asset, S: Source + Send + Sync>(cache: &AssetCache<S>, id: &str) -> Result<Self, io::Error> {
+        use rayon::prelude::*;
+
+        let ids = list_dir::<A, S>(cache.source(), id, false)?;
+
+        ids.par_iter().for_each(|id| {
+            let _ = cache.load::<A>(id);
+        });
+
+        Ok(Self::new(ids))
     }
 
-    #[cfg(feature = "hot-reloading")]
+    /// Creates a `CachedDir` with the given ids, which are sorted so that the
+    /// order of iteration does not depend on the source.
     #[inline]
-    pub fn remove(&self, id: &str) {
-        let mut list = self.assets.list.write();
+    pub fn new(mut ids: Vec<Box<str>>) -> Self {
+        ids.sort_unstable();
 
-        if let Some(pos) = list.iter().position(|s| s.as_ref() == id) {
-            list.remove(pos);
+        Self {
+            assets: Box::new(ids.into()),
         }
     }
 
+    /// Locks the ids of the directory for writing.
+    ///
+    /// If `wait` is `false` and the ids are currently being read, `None` is
+    /// returned instead of blocking.
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    pub fn write_ids(&self, wait: bool) -> Option<IdsMut<'_>> {
+        let list = if wait {
+            self.assets.list.write()
+        } else {
+            self.assets.list.try_write()?
+        };
+        Some(IdsMut(list))
+    }
+
     #[inline]
-    pub unsafe fn read<'a, A>(&self, cache: &'a AssetCache) -> DirReader<'a, A> {
+    pub unsafe fn read<'a, A, S>(&self, cache: &'a AssetCache<S>) -> DirReader<'a, A, S> {
         DirReader {
             cache,
             assets: &*(&*self.assets as *const StringList),
@@ -182,6 +308,45 @@ impl CachedDir {
     }
 }
 
+/// The ids of a directory, locked for writing.
+#[cfg(feature = "hot-reloading")]
+pub(crate) struct IdsMut<'a>(RwLockWriteGuard<'a, Vec<Box<str>>>);
+
+#[cfg(feature = "hot-reloading")]
+impl IdsMut<'_> {
+    /// Adds an id, and returns `true` if it was not there yet.
+    #[inline]
+    pub fn add(&mut self, id: Box<str>) -> bool {
+        match self.0.binary_search(&id) {
+            Ok(_) => false,
+            Err(pos) => {
+                self.0.insert(pos, id);
+                true
+            },
+        }
+    }
+
+    /// Removes an id, and returns `true` if it was there.
+    #[inline]
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.0.binary_search_by(|s| (**s).cmp(id)) {
+            Ok(pos) => {
+                self.0.remove(pos);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Removes all ids that are in the given directory.
+    pub fn remove_dir(&mut self, id: &str) -> bool {
+        let len = self.0.len();
+
+        self.0.retain(|s| !is_in_dir(id, s));
+        self.0.len() != len
+    }
+}
+
 impl fmt::Debug for CachedDir {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_list().entries(&*self.assets.list.read()).finish()
@@ -195,26 +360,28 @@ impl fmt::Debug for CachedDir {
 /// When [hot-reloading] is used, added/removed files will be added/removed from
 /// this structure.
 ///
+/// Assets are iterated in the lexicographic order of their ids.
+///
 /// This structure can be obtained by calling [`AssetCache::load_dir`].
 ///
 /// [`AssetCache::load_dir`]: struct.AssetCache.html#method.load_dir
 /// [hot-reloading]: struct.AssetCache.html#method.hot_reload
-pub struct DirReader<'a, A> {
-    cache: &'a AssetCache,
+pub struct DirReader<'a, A, S = FileSystem> {
+    cache: &'a AssetCache<S>,
     assets: &'a StringList,
     _marker: PhantomData<&'a A>,
 }
 
-impl<A> Clone for DirReader<'_, A> {
+impl<A, S> Clone for DirReader<'_, A, S> {
     #[inline]
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<A> Copy for DirReader<'_, A> {}
+impl<A, S> Copy for DirReader<'_, A, S> {}
 
-impl<'a, A: Asset> DirReader<'a, A> {
+impl<'a, A: Asset, S: Source> DirReader<'a, A, S> {
     /// An iterator over successfully loaded assets in a directory.
     ///
     /// This iterator yields each asset that was successfully loaded. It is
@@ -226,7 +393,7 @@ impl<'a, A: Asset> DirReader<'a, A> {
     ///
     /// [removed from the cache]: struct.AssetCache.html#method.remove
     #[inline]
-    pub fn iter(&self) -> ReadDir<'a, A> {
+    pub fn iter(&self) -> ReadDir<'a, A, S> {
         ReadDir {
             cache: self.cache,
             iter: self.assets.into_iter(),
@@ -241,7 +408,7 @@ impl<'a, A: Asset> DirReader<'a, A> {
     /// any asset that is not in the cache (e.g. that previously failed to load
     /// or was removed).
     #[inline]
-    pub fn iter_all(&self) -> ReadAllDir<'a, A> {
+    pub fn iter_all(&self) -> ReadAllDir<'a, A, S> {
         ReadAllDir {
             cache: self.cache,
             iter: self.assets.into_iter(),
@@ -250,16 +417,17 @@ impl<'a, A: Asset> DirReader<'a, A> {
     }
 }
 
-impl<'a, A> IntoIterator for &DirReader<'a, A>
+impl<'a, A, S> IntoIterator for &DirReader<'a, A, S>
 where
     A: Asset,
+    S: Source,
 {
     type Item = AssetRef<'a, A>;
-    type IntoIter = ReadDir<'a, A>;
+    type IntoIter = ReadDir<'a, A, S>;
 
     /// Equivalent to [`iter`](#method.iter).
     #[inline]
-    fn into_iter(self) -> ReadDir<'a, A> {
+    fn into_iter(self) -> ReadDir<'a, A, S> {
         self.iter()
     }
 }
@@ -271,15 +439,16 @@ where
 /// It can be obtained by calling [`DirReader::iter`].
 ///
 /// [`DirReader::iter`]: struct.DirReader.html#method.iter
-pub struct ReadDir<'a, A> {
-    cache: &'a AssetCache,
+pub struct ReadDir<'a, A, S = FileSystem> {
+    cache: &'a AssetCache<S>,
     iter: StringIter<'a>,
     _marker: PhantomData<&'a A>,
 }
 
-impl<'a, A> Iterator for ReadDir<'a, A>
+impl<'a, A, S> Iterator for ReadDir<'a, A, S>
 where
     A: Asset,
+    S: Source,
 {
     type Item = AssetRef<'a, A>;
 
@@ -300,7 +469,7 @@ where
     }
 }
 
-impl<A> FusedIterator for ReadDir<'_, A> where A: Asset {}
+impl<A, S> FusedIterator for ReadDir<'_, A, S> where A: Asset, S: Source {}
 
 /// An iterator over all assets in a directory.
 ///
@@ -310,15 +479,16 @@ impl<A> FusedIterator for ReadDir<'_, A> where A: Asset {}
 /// It can be obtained by calling [`DirReader::iter_all`].
 ///
 /// [`DirReader::iter_all`]: struct.DirReader.html#method.iter_all
-pub struct ReadAllDir<'a, A> {
-    cache: &'a AssetCache,
+pub struct ReadAllDir<'a, A, S = FileSystem> {
+    cache: &'a AssetCache<S>,
     iter: StringIter<'a>,
     _marker: PhantomData<&'a A>,
 }
 
-impl<'a, A> Iterator for ReadAllDir<'a, A>
+impl<'a, A, S> Iterator for ReadAllDir<'a, A, S>
 where
     A: Asset,
+    S: Source,
 {
     type Item = (&'a str, Result<AssetRef<'a, A>, AssetError<A>>);
 
@@ -334,9 +504,10 @@ where
     }
 }
 
-impl<A> ExactSizeIterator for ReadAllDir<'_, A>
+impl<A, S> ExactSizeIterator for ReadAllDir<'_, A, S>
 where
     A: Asset,
+    S: Source,
 {
     #[inline]
     fn len(&self) -> usize {
@@ -344,18 +515,19 @@ where
     }
 }
 
-impl<A> FusedIterator for ReadAllDir<'_, A> where A: Asset {}
+impl<A, S> FusedIterator for ReadAllDir<'_, A, S> where A: Asset, S: Source {}
 
-impl<A> fmt::Debug for DirReader<'_, A>
+impl<A, S> fmt::Debug for DirReader<'_, A, S>
 where
     A: fmt::Debug + Asset,
+    S: Source,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_list().entries(self.iter()).finish()
     }
 }
 
-impl<A> fmt::Debug for ReadDir<'_, A>
+impl<A, S> fmt::Debug for ReadDir<'_, A, S>
 where
     A: fmt::Debug,
 {
@@ -364,7 +536,7 @@ where
     }
 }
 
-impl<A> fmt::Debug for ReadAllDir<'_, A>
+impl<A, S> fmt::Debug for ReadAllDir<'_, A, S>
 where
     A: fmt::Debug,
 {
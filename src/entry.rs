@@ -1,35 +1,177 @@
 //! Definitions of cache entries
 
 use std::{
-    any::Any,
+    any::{Any, TypeId},
     fmt,
     hash,
     ops::Deref,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
 };
 
+#[cfg(not(feature = "arc-swap"))]
 use crate::utils::{RwLock, RwLockReadGuard};
 
+#[cfg(feature = "arc-swap")]
+use std::marker::PhantomData;
+
+
+/// The storage of an asset.
+///
+/// By default, this is a `RwLock`, so reading an asset blocks writers. With
+/// feature `arc-swap`, the asset is stored in an `Arc` that is atomically
+/// swapped when it is written, and readers keep a snapshot of it.
+#[cfg(not(feature = "arc-swap"))]
+struct Lock<T>(RwLock<T>);
+
+#[cfg(not(feature = "arc-swap"))]
+impl<T> Lock<T> {
+    #[inline]
+    fn new(value: T) -> Self {
+        Lock(RwLock::new(value))
+    }
+
+    #[inline]
+    fn read(&self) -> LockGuard<'_, T> {
+        self.0.read()
+    }
+
+    #[inline]
+    fn write(&self, value: T) {
+        *self.0.write() = value;
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn try_write(&self, value: T) -> Result<(), T> {
+        match self.0.try_write() {
+            Some(mut guard) => {
+                *guard = value;
+                Ok(())
+            },
+            None => Err(value),
+        }
+    }
+
+    #[inline]
+    fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+#[cfg(not(feature = "arc-swap"))]
+type LockGuard<'a, T> = RwLockReadGuard<'a, T>;
+
+#[cfg(feature = "arc-swap")]
+struct Lock<T>(arc_swap::ArcSwap<T>);
+
+#[cfg(feature = "arc-swap")]
+impl<T> Lock<T> {
+    #[inline]
+    fn new(value: T) -> Self {
+        Lock(arc_swap::ArcSwap::from_pointee(value))
+    }
+
+    #[inline]
+    fn read(&self) -> LockGuard<'_, T> {
+        LockGuard(self.0.load_full(), PhantomData)
+    }
+
+    #[inline]
+    fn write(&self, value: T) {
+        self.0.store(Arc::new(value));
+    }
+
+    /// Writing never blocks with `arc-swap`.
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn try_write(&self, value: T) -> Result<(), T> {
+        self.write(value);
+        Ok(())
+    }
+
+    #[inline]
+    fn into_inner(self) -> T {
+        // Snapshots cannot outlive the lock, so this is the only reference
+        match Arc::try_unwrap(self.0.into_inner()) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+/// A snapshot of an asset, which does not prevent it from being written.
+#[cfg(feature = "arc-swap")]
+struct LockGuard<'a, T>(Arc<T>, PhantomData<&'a T>);
+
+#[cfg(feature = "arc-swap")]
+impl<T> Deref for LockGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+
+/// Data used to decide which entries can be evicted from the cache.
+struct Meta {
+    /// The estimated size of the asset, in bytes
+    size: AtomicUsize,
+    /// The tick of the last access to the entry
+    last_access: AtomicUsize,
+    /// Whether an `AssetRef` was created from the entry
+    borrowed: AtomicBool,
+}
+
 struct Inner<T> {
-    lock: RwLock<T>,
+    lock: Lock<T>,
     reload: AtomicUsize,
+    meta: Meta,
 }
 
 impl<T> Inner<T> {
     #[inline]
     fn new(value: T) -> Self {
         Self {
-            lock: RwLock::new(value),
+            lock: Lock::new(value),
             reload: AtomicUsize::new(0),
+            meta: Meta {
+                size: AtomicUsize::new(0),
+                last_access: AtomicUsize::new(0),
+                borrowed: AtomicBool::new(false),
+            },
         }
     }
 
     #[inline]
     fn write(&self, value: T) {
-        let mut data = self.lock.write();
-        *data = value;
+        self.lock.write(value);
         self.reload.fetch_add(1, Ordering::Release);
     }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn try_write(&self, value: T) -> Result<(), T> {
+        self.lock.try_write(value)?;
+        self.reload.fetch_add(1, Ordering::Release);
+        Ok(())
+    }
+}
+
+
+trait AnyInner: Any + Send + Sync {
+    fn meta(&self) -> &Meta;
+}
+
+impl<T: Send + Sync + 'static> AnyInner for Inner<T> {
+    #[inline]
+    fn meta(&self) -> &Meta {
+        &self.meta
+    }
 }
 
 
@@ -38,12 +180,16 @@ impl<T> Inner<T> {
 /// # Safety
 ///
 /// - Methods that are generic over `T` can only be called with the same `T` used
-/// to create them.
+///   to create them.
 /// - When an `AssetRef<'a, T>` is returned, you have to ensure that `self`
-/// outlives it. The `CacheEntry` can be moved but cannot be dropped.
+///   outlives it. The `CacheEntry` can be moved but cannot be dropped, unless
+///   [`is_evictable`] returns `true`.
+///
+/// The inner value is shared with [`Handle`]s, so it may outlive the entry.
 ///
-/// [`ContreteCacheEntry`]: struct.ContreteCacheEntry.html
-pub(crate) struct CacheEntry(Box<dyn Any + Send + Sync>);
+/// [`Handle`]: struct.Handle.html
+/// [`is_evictable`]: #method.is_evictable
+pub(crate) struct CacheEntry(Arc<dyn AnyInner>);
 
 impl<'a> CacheEntry {
     /// Creates a new `CacheEntry` containing an asset of type `T`.
@@ -51,7 +197,7 @@ impl<'a> CacheEntry {
     /// The returned structure can safely use its methods with type parameter `T`.
     #[inline]
     pub fn new<T: Send + Sync + 'static>(asset: T) -> Self {
-        CacheEntry(Box::new(Inner::new(asset)))
+        CacheEntry(Arc::new(Inner::new(asset)))
     }
 
     /// Returns a reference to the underlying lock.
@@ -61,16 +207,30 @@ impl<'a> CacheEntry {
     /// See type-level documentation.
     #[inline]
     pub unsafe fn get_ref<T: Send + Sync + 'static>(&self) -> AssetRef<'a, T> {
-        debug_assert!(self.0.is::<Inner<T>>());
+        debug_assert!(Any::type_id(&*self.0) == TypeId::of::<Inner<T>>());
 
+        self.0.meta().borrowed.store(true, Ordering::Relaxed);
         let data = {
-            let ptr = &*self.0 as *const dyn Any as *const Inner<T>;
+            let ptr = &*self.0 as *const dyn AnyInner as *const Inner<T>;
             &*ptr
         };
 
         AssetRef::new(data)
     }
 
+    /// Returns a handle to the underlying lock.
+    ///
+    /// # Safety
+    ///
+    /// See type-level documentation.
+    #[inline]
+    pub unsafe fn get_handle<T: Send + Sync + 'static>(&self) -> Handle<T> {
+        debug_assert!(Any::type_id(&*self.0) == TypeId::of::<Inner<T>>());
+
+        let ptr = Arc::into_raw(self.0.clone()) as *const Inner<T>;
+        Handle::new(Arc::from_raw(ptr))
+    }
+
     /// Write a value and a get reference to the underlying lock
     ///
     /// # Safety
@@ -82,17 +242,111 @@ impl<'a> CacheEntry {
         lock
     }
 
+    /// Writes a value, without marking the entry as borrowed.
+    ///
+    /// # Safety
+    ///
+    /// See type-level documentation.
+    #[cfg(feature = "hot-reloading")]
+    pub unsafe fn replace<T: Send + Sync + 'static>(&self, asset: T) {
+        debug_assert!(Any::type_id(&*self.0) == TypeId::of::<Inner<T>>());
+
+        let data = &*(&*self.0 as *const dyn AnyInner as *const Inner<T>);
+        data.write(asset);
+    }
+
+    /// Writes a value without blocking, unless the underlying lock is
+    /// currently held, in which case the value is given back.
+    ///
+    /// Unlike [`write`], this does not mark the entry as borrowed.
+    ///
+    /// # Safety
+    ///
+    /// See type-level documentation.
+    ///
+    /// [`write`]: #method.write
+    #[cfg(feature = "hot-reloading")]
+    pub unsafe fn try_write<T: Send + Sync + 'static>(&self, asset: T) -> Result<(), T> {
+        debug_assert!(Any::type_id(&*self.0) == TypeId::of::<Inner<T>>());
+
+        let data = &*(&*self.0 as *const dyn AnyInner as *const Inner<T>);
+        data.try_write(asset)
+    }
+
     /// Consumes the `CacheEntry` and returns its inner value.
     ///
+    /// If the value is still shared with a [`Handle`], the entry is given
+    /// back.
+    ///
     /// # Safety
     ///
     /// See type-level documentation.
+    ///
+    /// [`Handle`]: struct.Handle.html
+    #[inline]
+    pub unsafe fn into_inner<T: Send + Sync + 'static>(self) -> Result<T, Self> {
+        debug_assert!(Any::type_id(&*self.0) == TypeId::of::<Inner<T>>());
+
+        let inner = Arc::from_raw(Arc::into_raw(self.0) as *const Inner<T>);
+        match Arc::try_unwrap(inner) {
+            Ok(inner) => Ok(inner.lock.into_inner()),
+            Err(inner) => Err(CacheEntry(inner)),
+        }
+    }
+
+    /// Returns the estimated size of the asset, in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.0.meta().size.load(Ordering::Relaxed)
+    }
+
+    /// Sets the estimated size of the asset and returns the previous one.
+    #[inline]
+    pub fn set_size(&self, size: usize) -> usize {
+        self.0.meta().size.swap(size, Ordering::Relaxed)
+    }
+
+    /// Returns the tick of the last access to the entry.
+    #[inline]
+    pub fn last_access(&self) -> usize {
+        self.0.meta().last_access.load(Ordering::Relaxed)
+    }
+
+    /// Records an access to the entry at the given tick.
+    #[inline]
+    pub fn touch(&self, tick: usize) {
+        self.0.meta().last_access.store(tick, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if an `AssetRef` was created from the entry, in which
+    /// case it must not be dropped while the cache is shared.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        self.0.meta().borrowed.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the entry can be dropped while the cache is shared,
+    /// ie if no `AssetRef` or `Handle` was created from it.
+    ///
+    /// This must be called while no other thread can access the entry.
+    #[inline]
+    pub fn is_evictable(&self) -> bool {
+        !self.is_borrowed() && !self.is_shared()
+    }
+
+    /// Returns `true` if the entry is shared with a `Handle`.
     #[inline]
-    pub unsafe fn into_inner<T: Send + Sync + 'static>(self) -> T {
-        debug_assert!(self.0.is::<Inner<T>>());
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.0) != 1
+    }
 
-        let inner = Box::from_raw(Box::into_raw(self.0) as *mut Inner<T>);
-        inner.lock.into_inner()
+    /// Marks the entry as no longer borrowed by an `AssetRef`.
+    ///
+    /// This must only be called when no `AssetRef` to the entry can be alive,
+    /// eg with a mutable reference to the cache.
+    #[inline]
+    pub fn release(&mut self) {
+        self.0.meta().borrowed.store(false, Ordering::Relaxed);
     }
 }
 
@@ -109,7 +363,9 @@ impl fmt::Debug for CacheEntry {
 ///
 /// This structure wraps a RwLock, so assets can be written to be reloaded. As
 /// such, any number of read guard can exist at the same time, but none can
-/// exist while reloading an asset.
+/// exist while reloading an asset. With feature `arc-swap`, reloading an asset
+/// atomically replaces it instead, so read guards never block reloads, but
+/// they keep seeing the previous value until they are dropped.
 ///
 /// This is the structure you want to use to store a reference to an asset.
 /// However, data shared between threads is usually required to be `'static`,
@@ -119,9 +375,10 @@ impl fmt::Debug for CacheEntry {
 /// (for example with `lazy_static` crate or by [leaking a `Box`]), but doing
 /// this prevents from removing assets from the cache. Another solution is to
 /// use crates that allow threads with non-static data (such as
-/// `crossbeam-utils::scope`).
+/// `crossbeam-utils::scope`), or to use a [`Handle`] instead.
 ///
 /// [leaking a `Box`]: https://doc.rust-lang.org/std/boxed/struct.Box.html#method.leak
+/// [`Handle`]: struct.Handle.html
 pub struct AssetRef<'a, A> {
     data: &'a Inner<A>,
     last_reload: usize,
@@ -261,15 +518,134 @@ where
 }
 
 
+/// An owned handle on an asset.
+///
+/// Unlike an [`AssetRef`], a `Handle` does not borrow the [`AssetCache`] it
+/// comes from, so it can be stored anywhere, or sent to another thread. It is
+/// obtained with [`AssetCache::load_handle`], and is cheap to clone.
+///
+/// A `Handle` keeps its asset alive, even if it is removed from the cache (eg
+/// with [`AssetCache::remove`] or [`AssetCache::clear`]). However, the asset is
+/// then not updated by hot-reloading anymore, even if it is loaded again.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{Asset, AssetCache, Handle};
+/// # use assets_manager::loader::{LoadFrom, ParseLoader};
+///
+/// struct Example(i32);
+/// # impl From<i32> for Example {
+/// #     fn from(n: i32) -> Self { Self(n) }
+/// # }
+/// impl Asset for Example {
+///     /* ... */
+///     # const EXTENSION: &'static str = "x";
+///     # type Loader = LoadFrom<i32, ParseLoader>;
+/// }
+///
+/// let mut cache = AssetCache::new("assets")?;
+/// let handle: Handle<Example> = cache.load_handle("example.reload")?;
+///
+/// // The handle outlives the cache entry
+/// cache.clear();
+///
+/// std::thread::spawn(move || {
+///     assert_eq!(handle.read().0, 21);
+/// }).join().unwrap();
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetRef`]: struct.AssetRef.html
+/// [`AssetCache`]: struct.AssetCache.html
+/// [`AssetCache::load_handle`]: struct.AssetCache.html#method.load_handle
+/// [`AssetCache::remove`]: struct.AssetCache.html#method.remove
+/// [`AssetCache::clear`]: struct.AssetCache.html#method.clear
+pub struct Handle<A> {
+    data: Arc<Inner<A>>,
+    last_reload: usize,
+}
+
+impl<A> Handle<A> {
+    #[inline]
+    fn new(inner: Arc<Inner<A>>) -> Self {
+        Self {
+            last_reload: inner.reload.load(Ordering::Acquire),
+            data: inner,
+        }
+    }
+
+    /// Locks the pointed asset for reading.
+    ///
+    /// Returns a RAII guard which will release the lock once dropped.
+    #[inline]
+    pub fn read(&self) -> AssetGuard<'_, A> {
+        AssetGuard {
+            guard: self.data.lock.read(),
+        }
+    }
+
+    /// Returns `true` if the asset has been reloaded since last call to this
+    /// method with this `Handle`.
+    ///
+    /// See [`AssetRef::reloaded`] for more details.
+    ///
+    /// [`AssetRef::reloaded`]: struct.AssetRef.html#method.reloaded
+    pub fn reloaded(&mut self) -> bool {
+        let last_reload = self.data.reload.load(Ordering::Acquire);
+
+        if last_reload > self.last_reload {
+            self.last_reload = last_reload;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns an `AssetRef` borrowing this handle.
+    #[inline]
+    pub fn as_asset_ref(&self) -> AssetRef<'_, A> {
+        AssetRef::new(&self.data)
+    }
+
+    /// Checks if the two handles refer to the same cache entry.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl<A> Clone for Handle<A> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            last_reload: self.last_reload,
+        }
+    }
+}
+
+impl<A> fmt::Debug for Handle<A>
+where
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle").field("data", &*self.data.lock.read()).finish()
+    }
+}
+
+
 /// RAII guard used to keep a read lock on an asset and release it when dropped.
 ///
 /// This type is a smart pointer to type `A`.
 ///
+/// With feature `arc-swap`, this is a snapshot of the asset, which is not
+/// affected if the asset is reloaded meanwhile.
+///
 /// It can be obtained by calling [`AssetRef::read`].
 ///
 /// [`AssetRef::read`]: struct.AssetRef.html#method.read
 pub struct AssetGuard<'a, A> {
-    guard: RwLockReadGuard<'a, A>,
+    guard: LockGuard<'a, A>,
 }
 
 impl<A> Deref for AssetGuard<'_, A> {
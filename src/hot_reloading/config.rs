@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use crate::dirs;
+
+
+/// Settings of hot-reloading.
+///
+/// A cache is created with these settings with [`AssetCache::with_config`].
+/// The default settings watch the whole source with notifications of the
+/// operating system, and do not ignore any file.
+///
+/// # Example
+///
+/// ```no_run
+/// use assets_manager::{AssetCache, hot_reloading::HotReloadConfig, source::FileSystem};
+/// use std::time::Duration;
+///
+/// let config = HotReloadConfig::new()
+///     .with_debounce(Duration::from_millis(200))
+///     .with_ignored("*.swp")
+///     .with_ignored("*~")
+///     .with_ignored("*.tmp")
+///     .with_dir("textures")
+///     .with_dir("sounds");
+///
+/// let source = FileSystem::new("assets")?;
+/// let cache = AssetCache::with_config(source, &config)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::with_config`]: ../struct.AssetCache.html#method.with_config
+#[derive(Debug, Clone)]
+pub struct HotReloadConfig {
+    debounce: Duration,
+    update_interval: Duration,
+    polling: Option<Duration>,
+    ignored: Vec<Box<str>>,
+    dirs: Vec<Box<str>>,
+}
+
+impl HotReloadConfig {
+    /// Creates the default settings.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            debounce: Duration::from_millis(50),
+            update_interval: Duration::from_millis(20),
+            polling: None,
+            ignored: Vec::new(),
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Sets the delay used by watchers to merge events that happen in quick
+    /// succession. Defaults to 50 ms.
+    ///
+    /// This is only used by watchers that rely on notifications of the
+    /// operating system, and is ignored with [`with_polling`].
+    ///
+    /// [`with_polling`]: #method.with_polling
+    #[inline]
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Sets the interval at which the background thread collects the events
+    /// of the watcher. Defaults to 20 ms.
+    ///
+    /// With [`AssetCache::enhance_hot_reloading`], this is also the interval
+    /// at which changes are applied to the cache.
+    ///
+    /// [`AssetCache::enhance_hot_reloading`]: ../struct.AssetCache.html#method.enhance_hot_reloading
+    #[inline]
+    pub fn with_update_interval(mut self, interval: Duration) -> Self {
+        self.update_interval = interval;
+        self
+    }
+
+    /// Makes hot-reloading poll the file system at the given interval instead
+    /// of relying on notifications of the operating system.
+    ///
+    /// Notifications are not sent for some file systems, such as network file
+    /// systems (NFS, SMB) or some volumes mounted in containers. With this
+    /// option, the modification time and the size of the files used by the
+    /// cache are checked periodically to detect changes. Only files and
+    /// directories that were loaded are checked.
+    ///
+    /// Files are checked when events are collected, if `interval` elapsed
+    /// since the previous check, so changes may be detected up to `interval`
+    /// plus the [update interval] after they happen.
+    ///
+    /// This only affects [`FileSystem`] sources, including those used in an
+    /// [`Overlay`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use assets_manager::{AssetCache, hot_reloading::HotReloadConfig, source::FileSystem};
+    /// use std::time::Duration;
+    ///
+    /// let config = HotReloadConfig::new().with_polling(Duration::from_secs(1));
+    /// let cache = AssetCache::with_config(FileSystem::new("assets")?, &config)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// [update interval]: #method.with_update_interval
+    /// [`FileSystem`]: ../source/struct.FileSystem.html
+    /// [`Overlay`]: ../source/struct.Overlay.html
+    #[inline]
+    pub fn with_polling(mut self, interval: Duration) -> Self {
+        self.polling = Some(interval);
+        self
+    }
+
+    /// Ignores changes to files whose name matches `pattern`.
+    ///
+    /// The pattern is matched against the file name with its extension (eg
+    /// `player.ron`), and a `*` in it matches any sequence of characters. This
+    /// is useful to ignore temporary files created by editors, such as
+    /// `*.swp`, `*~` or `*.tmp`.
+    #[inline]
+    pub fn with_ignored(mut self, pattern: &str) -> Self {
+        self.ignored.push(pattern.into());
+        self
+    }
+
+    /// Restricts hot-reloading to the directory with the given id and its
+    /// subdirectories.
+    ///
+    /// This can be called several times to watch several directories. If it is
+    /// never called, the whole source is watched. The directory does not need
+    /// to exist when the cache is created.
+    #[inline]
+    pub fn with_dir(mut self, id: &str) -> Self {
+        self.dirs.push(id.into());
+        self
+    }
+
+    /// Returns the delay used by watchers to merge events.
+    #[inline]
+    pub fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    /// Returns the interval at which the events of the watcher are collected.
+    #[inline]
+    pub fn update_interval(&self) -> Duration {
+        self.update_interval
+    }
+
+    /// Returns the interval at which the file system is polled, if polling is
+    /// used instead of notifications of the operating system.
+    #[inline]
+    pub fn polling(&self) -> Option<Duration> {
+        self.polling
+    }
+
+    /// Returns the ids of the watched directories.
+    ///
+    /// If this is empty, the whole source is watched.
+    #[inline]
+    pub fn dirs(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.dirs.iter().map(|dir| &**dir)
+    }
+
+    /// Returns `true` if changes to the given file should be reported.
+    pub fn is_watched(&self, id: &str, ext: &str) -> bool {
+        if !self.dirs.is_empty() && !self.dirs.iter().any(|dir| dirs::is_in_dir(dir, id)) {
+            return false;
+        }
+
+        let name = match id.rfind('.') {
+            Some(pos) => &id[pos+1..],
+            None => id,
+        };
+        if self.ignored.is_empty() {
+            return true;
+        }
+
+        let file_name;
+        let file_name = if ext.is_empty() {
+            name
+        } else {
+            file_name = format!("{}.{}", name, ext);
+            &file_name
+        };
+
+        !self.ignored.iter().any(|pattern| dirs::match_name(pattern, file_name))
+    }
+}
+
+impl Default for HotReloadConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,30 +1,209 @@
+//! Tools to implement hot-reloading.
+//!
+//! Hot-reloading is driven by [`Source`]s: a source that supports it returns a
+//! [`Watcher`] from [`Source::watch`]. The `AssetCache` polls this watcher from
+//! a background thread, reloads changed assets, and makes them visible on the
+//! next call to [`AssetCache::hot_reload`].
+//!
+//! [`Source`]: ../source/trait.Source.html
+//! [`Source::watch`]: ../source/trait.Source.html#method.watch
+//! [`Watcher`]: trait.Watcher.html
+//! [`AssetCache::hot_reload`]: ../struct.AssetCache.html#method.hot_reload
+
+mod config;
 mod paths;
+mod subscribers;
 
 #[cfg(test)]
 mod tests;
 
+pub use config::HotReloadConfig;
+
 pub(crate) use paths::WatchedPaths;
+pub(crate) use subscribers::Subscribers;
 use paths::FileCache;
 
 use std::{
+    collections::HashSet,
     fmt,
     mem::ManuallyDrop,
-    path::Path,
-    ptr::NonNull,
-    sync::mpsc::{self, channel, Receiver, Sender},
+    sync::{
+        Arc,
+        mpsc::{self, channel, Sender},
+    },
     thread,
-    time::Duration,
 };
 
-use notify::{DebouncedEvent, RecursiveMode, Watcher};
-
 use crate::{
     AssetCache,
+    compound::Dependency,
+    source::{DirEntry, Source},
+    utils::Mutex,
 };
 
 
-struct SharedPtr<T>(NonNull<T>);
-unsafe impl<T: Sync> Send for SharedPtr<T> {}
+/// A change in a [`Source`].
+///
+/// Ids are given the same way as in [`DirEntry`].
+///
+/// [`Source`]: ../source/trait.Source.html
+/// [`DirEntry`]: ../source/enum.DirEntry.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// A file was created or modified.
+    Changed {
+        /// The id of the file.
+        id: String,
+        /// The extension of the file.
+        ext: String,
+    },
+
+    /// A file was removed.
+    Removed {
+        /// The id of the file.
+        id: String,
+        /// The extension of the file.
+        ext: String,
+    },
+}
+
+/// Watches a [`Source`] for changes.
+///
+/// A `Watcher` is obtained from [`Source::watch`], and is then moved to a
+/// background thread which periodically polls it.
+///
+/// [`Source`]: ../source/trait.Source.html
+/// [`Source::watch`]: ../source/trait.Source.html#method.watch
+pub trait Watcher: Send {
+    /// Returns the source used to read changed files.
+    ///
+    /// This is usually a copy of the watched source.
+    fn source(&self) -> &dyn Source;
+
+    /// Calls `f` with each change since the last call to this method.
+    ///
+    /// This method should not block.
+    fn poll(&mut self, f: &mut dyn FnMut(Event));
+
+    /// Called when the cache starts watching a file or a directory of the
+    /// source.
+    ///
+    /// For directories, `recursive` tells whether their subdirectories are
+    /// watched too.
+    ///
+    /// Watchers that are notified of all changes of their source can ignore
+    /// this, which is what the default implementation does. Watchers that have
+    /// to poll their source can use it to only check the entries used by the
+    /// cache.
+    fn add_entry(&mut self, entry: DirEntry, recursive: bool) {
+        let _ = (entry, recursive);
+    }
+}
+
+
+/// What was done by a call to [`AssetCache::hot_reload`].
+///
+/// Entries are not given in any particular order.
+///
+/// [`AssetCache::hot_reload`]: ../struct.AssetCache.html#method.hot_reload
+#[derive(Debug, Clone, Default)]
+pub struct ReloadReport {
+    /// Assets and compounds that were reloaded.
+    pub reloaded: Vec<Reloaded>,
+
+    /// Assets and compounds that failed to reload, and were left unchanged.
+    pub failed: Vec<ReloadError>,
+
+    /// Changes of cached directories.
+    pub dirs: Vec<DirChange>,
+}
+
+impl ReloadReport {
+    /// Returns `true` if nothing happened.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.reloaded.is_empty() && self.failed.is_empty() && self.dirs.is_empty()
+    }
+}
+
+/// An asset that was reloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reloaded {
+    /// The id of the asset.
+    pub id: String,
+    /// The name of the type of the asset, as given by [`std::any::type_name`].
+    ///
+    /// [`std::any::type_name`]: https://doc.rust-lang.org/std/any/fn.type_name.html
+    pub type_name: &'static str,
+}
+
+/// An asset that could not be reloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReloadError {
+    /// The id of the asset.
+    pub id: String,
+    /// The extension of the changed file.
+    ///
+    /// Together with the id, it gives the path of the file within the source,
+    /// for example with [`FileSystem::path_of`]. This is `None` for compounds,
+    /// which are not loaded from a single file.
+    ///
+    /// [`FileSystem::path_of`]: ../source/struct.FileSystem.html#method.path_of
+    pub ext: Option<String>,
+    /// The name of the type of the asset, as given by [`std::any::type_name`].
+    ///
+    /// [`std::any::type_name`]: https://doc.rust-lang.org/std/any/fn.type_name.html
+    pub type_name: &'static str,
+    /// The message of the error returned by the loader.
+    pub message: String,
+}
+
+/// A change of a cached directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DirChange {
+    /// The id of the directory.
+    ///
+    /// For queries such as [`AssetCache::load_glob`], this is the pattern of
+    /// the query.
+    ///
+    /// [`AssetCache::load_glob`]: ../struct.AssetCache.html#method.load_glob
+    pub dir: String,
+    /// The id of the added or removed entry.
+    pub id: String,
+    /// What happened to the entry.
+    pub kind: DirChangeKind,
+}
+
+/// A change made to an `AssetCache` by hot-reloading.
+///
+/// Changes are sent to the channels returned by [`AssetCache::subscribe`],
+/// [`AssetCache::subscribe_dir`] and [`AssetCache::subscribe_all`] when
+/// [`AssetCache::hot_reload`] is called.
+///
+/// [`AssetCache::subscribe`]: ../struct.AssetCache.html#method.subscribe
+/// [`AssetCache::subscribe_dir`]: ../struct.AssetCache.html#method.subscribe_dir
+/// [`AssetCache::subscribe_all`]: ../struct.AssetCache.html#method.subscribe_all
+/// [`AssetCache::hot_reload`]: ../struct.AssetCache.html#method.hot_reload
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// An asset or a compound was given a new value.
+    Asset(Reloaded),
+    /// An entry was added to or removed from a directory.
+    Dir(DirChange),
+}
+
+/// The kind of a [`DirChange`].
+///
+/// [`DirChange`]: struct.DirChange.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirChangeKind {
+    /// An asset was added to the directory.
+    Added,
+    /// An asset was removed from the directory.
+    Removed,
+    /// A subdirectory and all its assets were removed from the directory.
+    RemovedDir,
+}
 
 
 struct JoinOnDrop(ManuallyDrop<thread::JoinHandle<()>>);
@@ -44,80 +223,125 @@ impl From<thread::JoinHandle<()>> for JoinOnDrop {
 }
 
 
-#[allow(unused)]
-pub struct HotReloader {
-    sender: Sender<SharedPtr<AssetCache>>,
-    receiver: Receiver<()>,
+/// A cache whose changes are applied by the hot-reloading thread.
+trait AnyCache: Send + Sync {
+    /// Applies the changes to the cache without blocking, and returns what
+    /// has changed.
+    fn update(&self, shared: &mut Shared) -> HashSet<Dependency>;
+
+    /// Rebuilds compounds without blocking.
+    fn rebuild_compounds(&self, changed: HashSet<Dependency>);
+}
+
+impl<S: Source + Send + Sync> AnyCache for AssetCache<S> {
+    fn update(&self, shared: &mut Shared) -> HashSet<Dependency> {
+        shared.update(self, &mut ReloadReport::default(), false)
+    }
+
+    fn rebuild_compounds(&self, changed: HashSet<Dependency>) {
+        self.rebuild_compounds(changed, &mut ReloadReport::default(), false);
+    }
+}
+
+
+/// State shared between the `HotReloader` and its thread.
+struct Shared {
+    watcher: Box<dyn Watcher>,
+    config: HotReloadConfig,
+    cache: FileCache,
+    /// Set if the thread applies changes itself.
+    enhanced: Option<&'static dyn AnyCache>,
+}
+
+impl Shared {
+    fn poll(&mut self) {
+        let Shared { watcher, config, cache, .. } = self;
+        let mut events = Vec::new();
+        watcher.poll(&mut |event| {
+            let (id, ext) = match &event {
+                Event::Changed { id, ext } | Event::Removed { id, ext } => (id, ext),
+            };
+            if config.is_watched(id, ext) {
+                events.push(event);
+            }
+        });
+
+        for event in events {
+            cache.handle(event, watcher.source());
+        }
+    }
+
+    /// Applies the changes to the cache, and returns what has changed.
+    fn update<S: Source>(&mut self, asset_cache: &AssetCache<S>, report: &mut ReloadReport, wait: bool) -> HashSet<Dependency> {
+        // Changes that have not been seen by the thread yet are processed
+        // here, so they are always visible after this call
+        self.cache.get_watched(&mut asset_cache.watched.lock(), &mut *self.watcher);
+        self.poll();
+        self.cache.update(asset_cache, report, wait)
+    }
+}
+
 
-    // The Watcher has to be dropped before the JoinHandle, so the spawned
+pub(crate) struct HotReloader {
+    shared: Arc<Mutex<Shared>>,
+
+    // The Sender has to be dropped before the JoinHandle, so the spawned
     // thread can be notified that it should end before we join on it
-    watcher: notify::RecommendedWatcher,
-    handle: JoinOnDrop,
+    _stop: Sender<()>,
+    _handle: JoinOnDrop,
 }
 
 
 impl HotReloader {
-    pub fn start(path: &Path) -> Result<Self, notify::Error> {
-        let (notify_tx, notify_rx) = channel();
-
-        let (ptr_tx, ptr_rx) = channel();
-        let (answer_tx, answer_rx) = channel();
+    pub fn start(watcher: Box<dyn Watcher>, config: &HotReloadConfig) -> Self {
+        let (stop_tx, stop_rx) = channel::<()>();
 
-        let mut watcher = notify::watcher(notify_tx, Duration::from_millis(50))?;
-        watcher.watch(path, RecursiveMode::Recursive)?;
+        let shared = Arc::new(Mutex::new(Shared {
+            watcher,
+            config: config.clone(),
+            cache: FileCache::new(),
+            enhanced: None,
+        }));
+        let thread_shared = shared.clone();
+        let interval = config.update_interval();
 
         let handle = thread::spawn(move || {
-            const TIMEOUT: Duration = Duration::from_millis(20);
-            let mut cache = FileCache::new();
-
-            loop {
-                match ptr_rx.recv_timeout(TIMEOUT) {
-                    Err(mpsc::RecvTimeoutError::Timeout) => (),
-                    Ok(SharedPtr(ptr)) => {
-                        {
-                            // Safety: The received pointer is guarantied to be
-                            // valid until we reply back
-                            let asset_cache = unsafe { ptr.as_ref() };
-                            cache.update(asset_cache);
-                            cache.get_watched(&mut asset_cache.watched.lock());
-                        }
-                        answer_tx.send(()).unwrap();
-                    },
-                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
-                }
+            // Nothing is ever sent through this channel, so it only returns
+            // when the `HotReloader` is dropped
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                let mut shared = thread_shared.lock();
+                match shared.enhanced {
+                    Some(cache) => {
+                        let changed = cache.update(&mut shared);
 
-                while let Ok(event) = notify_rx.try_recv() {
-                    match event {
-                        DebouncedEvent::Write(path)
-                        | DebouncedEvent::Chmod(path)
-                        | DebouncedEvent::Create(path) => {
-                            cache.load(path);
-                        },
-                        DebouncedEvent::Remove(path) => {
-                            cache.remove(path);
-                        },
-                        DebouncedEvent::Rename(src, dst) => {
-                            cache.load(dst);
-                            cache.remove(src);
-                        },
-                        _ => (),
-                    }
+                        // Compounds may load assets, which requires to lock
+                        // the `Shared`
+                        drop(shared);
+                        cache.rebuild_compounds(changed);
+                    },
+                    None => shared.poll(),
                 }
             }
         }).into();
 
-        Ok(HotReloader {
-            watcher,
-            handle,
+        HotReloader {
+            shared,
 
-            sender: ptr_tx,
-            receiver: answer_rx,
-        })
+            _stop: stop_tx,
+            _handle: handle,
+        }
     }
 
-    pub fn reload(&self, cache: &AssetCache) {
-        self.sender.send(SharedPtr(cache.into())).unwrap();
-        self.receiver.recv().unwrap();
+    /// Applies the changes to the cache, and returns what has changed.
+    ///
+    /// Reloaded assets and errors are added to `report`.
+    pub fn reload<S: Source>(&self, asset_cache: &AssetCache<S>, report: &mut ReloadReport) -> HashSet<Dependency> {
+        self.shared.lock().update(asset_cache, report, true)
+    }
+
+    /// Makes the thread apply changes to the given cache.
+    fn enhance(&self, cache: &'static dyn AnyCache) {
+        self.shared.lock().enhanced = Some(cache);
     }
 }
 
@@ -126,3 +350,40 @@ impl fmt::Debug for HotReloader {
         f.pad("HotReloader { .. }")
     }
 }
+
+
+impl<S> AssetCache<S>
+where
+    S: Source + Send + Sync + 'static,
+{
+    /// Makes hot-reloading apply changes in the background, without having to
+    /// call [`hot_reload`].
+    ///
+    /// This is meant for programs that do not have a main loop, such as tools
+    /// or servers. Changes are applied by the thread that watches the source,
+    /// so they can be observed with [`AssetRef::reloaded`] or with
+    /// [`subscribe`].
+    ///
+    /// The thread never waits for assets locked by an [`AssetGuard`] or for
+    /// directories being iterated: it leaves them unchanged, and tries again
+    /// a few milliseconds later. Calling [`hot_reload`] is still possible, and
+    /// applies changes immediately.
+    ///
+    /// The cache has to live as long as the program, which can be achieved
+    /// with [`Box::leak`] or with a `static` variable.
+    ///
+    /// If the cache's source does not support hot-reloading, this function
+    /// does nothing.
+    ///
+    /// [`hot_reload`]: #method.hot_reload
+    /// [`subscribe`]: #method.subscribe
+    /// [`AssetRef::reloaded`]: struct.AssetRef.html#method.reloaded
+    /// [`AssetGuard`]: struct.AssetGuard.html
+    /// [`Box::leak`]: https://doc.rust-lang.org/std/boxed/struct.Box.html#method.leak
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    pub fn enhance_hot_reloading(&'static self) {
+        if let Some(reloader) = &self.reloader {
+            reloader.enhance(self);
+        }
+    }
+}
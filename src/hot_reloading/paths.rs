@@ -1,21 +1,20 @@
 use std::{
     any::{Any, TypeId},
-    borrow::Cow,
-    fs,
-    io,
-    path::{Path, PathBuf},
+    collections::HashSet,
 };
 
 use crate::{
     Asset,
     AssetCache,
-    cache::Key,
-    dirs::{extension_of, id_push},
-    loader::Loader,
+    cache::{Key, load_sized},
+    compound::Dependency,
+    dirs::{Matcher, Recursive, is_in_dir},
     entry::CacheEntry,
+    source::{DirEntry, Source},
     utils::HashMap,
 };
 
+use super::{Change, DirChange, DirChangeKind, Event, ReloadError, ReloadReport, Reloaded, Watcher};
 
 
 struct Types<T>(Vec<(TypeId, T)>);
@@ -41,50 +40,97 @@ impl<T> Types<T> {
             self.0.push((type_id, t));
         }
     }
+
+    #[inline]
+    fn retain(&mut self, mut f: impl FnMut(TypeId, &T) -> bool) {
+        self.0.retain(|(id, t)| f(*id, t));
+    }
+}
+
+/// Removes the types that do not match `f` for an id, and the id itself if no
+/// type remain.
+fn retain_types<T>(map: &mut HashMap<Box<str>, Types<T>>, id: &str, f: impl FnMut(TypeId, &T) -> bool) {
+    if let Some(types) = map.get_mut(id) {
+        types.retain(f);
+        if types.0.is_empty() {
+            map.remove(id);
+        }
+    }
 }
 
 
-fn borrowed(content: &io::Result<Vec<u8>>) -> io::Result<Cow<[u8]>> {
-    match content {
-        Ok(bytes) => Ok(bytes.into()),
-        Err(err) => match err.raw_os_error() {
-            Some(e) => Err(io::Error::from_raw_os_error(e)),
-            None => Err(err.kind().into()),
-        },
+/// Returns `true` if a file exists with the given id and one of the given
+/// extensions.
+fn exists_with(source: &dyn Source, id: &str, exts: Ext) -> bool {
+    exts.iter().any(|ext| source.exists(DirEntry::File(id, ext)))
+}
+
+/// Splits an id between its parent directory and its name.
+fn split_id(id: &str) -> (&str, &str) {
+    match id.rfind('.') {
+        Some(pos) => (&id[..pos], &id[pos+1..]),
+        None => ("", id),
     }
 }
 
-fn clone_and_push(id: &str, name: &str) -> Box<str> {
-    let mut id = id.to_string();
-    id_push(&mut id, name);
-    id.into()
+/// Returns the ids of all directories that contain the given id, from the
+/// closest to the root.
+fn ancestors(mut id: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        if id.is_empty() {
+            None
+        } else {
+            id = split_id(id).0;
+            Some(id)
+        }
+    })
 }
 
 
 trait AnyAsset: Any + Send + Sync {
-    unsafe fn reload(self: Box<Self>, entry: &CacheEntry);
+    /// Writes the asset in the entry. If `wait` is `false` and the entry is
+    /// locked, the asset is given back.
+    unsafe fn reload(self: Box<Self>, entry: &CacheEntry, wait: bool) -> Result<(), Box<dyn AnyAsset>>;
     fn create(self: Box<Self>) -> CacheEntry;
+    fn type_name(&self) -> &'static str;
 }
 
 impl<A: Asset> AnyAsset for A {
-    unsafe fn reload(self: Box<Self>, entry: &CacheEntry) {
-        entry.write::<A>(*self);
+    unsafe fn reload(self: Box<Self>, entry: &CacheEntry, wait: bool) -> Result<(), Box<dyn AnyAsset>> {
+        if wait {
+            entry.replace::<A>(*self);
+            Ok(())
+        } else {
+            entry.try_write::<A>(*self).map_err(|asset| Box::new(asset) as _)
+        }
     }
 
     fn create(self: Box<Self>) -> CacheEntry {
         CacheEntry::new::<A>(*self)
     }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<A>()
+    }
 }
 
 
-type LoadFn = fn(content: io::Result<Cow<[u8]>>, ext: &str, id: &str, path: &Path) -> Option<Box<dyn AnyAsset>>;
+/// A loaded asset, with its size.
+type Loaded = (Box<dyn AnyAsset>, usize);
 
-fn load<A: Asset>(content: io::Result<Cow<[u8]>>, ext: &str, id: &str, path: &Path) -> Option<Box<dyn AnyAsset>> {
-    match A::Loader::load(content, ext) {
-        Ok(asset) => Some(Box::new(asset)),
+type LoadFn = fn(source: &dyn Source, id: &str, ext: &str) -> Result<Loaded, ReloadError>;
+
+fn load<A: Asset>(source: &dyn Source, id: &str, ext: &str) -> Result<Loaded, ReloadError> {
+    match load_sized::<A, _>(source, id) {
+        Ok((asset, size)) => Ok((Box::new(asset), size)),
         Err(e) => {
-            log::warn!("Error reloading {:?} from {:?}: {}", id, path, e);
-            None
+            log::debug!("Error reloading {:?}: {}", id, e);
+            Err(ReloadError {
+                id: id.into(),
+                ext: Some(ext.into()),
+                type_name: std::any::type_name::<A>(),
+                message: e.to_string(),
+            })
         },
     }
 }
@@ -93,11 +139,18 @@ type Ext = &'static [&'static str];
 
 enum Kind {
     Asset,
-    Dir(Ext),
+    Dir,
+    /// A recursive directory, with the type used in its key
+    RecursiveDir(TypeId),
+    /// A query, with the type used in its key and the directory that
+    /// contains all its assets
+    Query(TypeId, Matcher, Box<str>),
 }
 
-pub struct WatchedPaths {
-    added: Vec<(PathBuf, Box<str>, TypeId, LoadFn, Kind)>,
+pub(crate) struct WatchedPaths {
+    added: Vec<(Box<str>, TypeId, LoadFn, Ext, Kind)>,
+    removed_assets: Vec<Key>,
+    removed_dirs: Vec<Key>,
     cleared: bool,
 }
 
@@ -105,52 +158,104 @@ impl WatchedPaths {
     pub fn new() -> Self {
         Self {
             added: Vec::new(),
+            removed_assets: Vec::new(),
+            removed_dirs: Vec::new(),
             cleared: false,
         }
     }
 
     #[inline]
-    pub fn add_file<A: Asset>(&mut self, path: PathBuf, id: Box<str>) {
-        self.added.push((path, id, TypeId::of::<A>(), load::<A>, Kind::Asset));
+    pub fn add_file<A: Asset>(&mut self, id: Box<str>) {
+        self.added.push((id, TypeId::of::<A>(), load::<A>, A::EXTENSIONS, Kind::Asset));
+    }
+
+    #[inline]
+    pub fn add_dir<A: Asset>(&mut self, id: Box<str>) {
+        self.added.push((id, TypeId::of::<A>(), load::<A>, A::EXTENSIONS, Kind::Dir));
+    }
+
+    #[inline]
+    pub fn add_recursive_dir<A: Asset>(&mut self, id: Box<str>) {
+        let kind = Kind::RecursiveDir(TypeId::of::<Recursive<A>>());
+        self.added.push((id, TypeId::of::<A>(), load::<A>, A::EXTENSIONS, kind));
     }
 
     #[inline]
-    pub fn add_dir<A: Asset>(&mut self, path: PathBuf, id: Box<str>) {
-        self.added.push((path, id, TypeId::of::<A>(), load::<A>, Kind::Dir(A::EXTENSIONS)));
+    pub fn add_query<A: Asset, Q: 'static>(&mut self, id: Box<str>, root: Box<str>, matcher: Matcher) {
+        let kind = Kind::Query(TypeId::of::<Q>(), matcher, root);
+        self.added.push((id, TypeId::of::<A>(), load::<A>, A::EXTENSIONS, kind));
+    }
+
+    /// Stops watching an asset that was removed from the cache.
+    pub fn remove_asset(&mut self, key: Key) {
+        self.added.retain(|(id, type_id, _, _, kind)| {
+            !(matches!(kind, Kind::Asset) && **id == *key.id() && *type_id == key.type_id())
+        });
+        self.removed_assets.push(key);
+    }
+
+    /// Stops watching a directory that was removed from the cache.
+    pub fn remove_dir(&mut self, key: Key) {
+        self.added.retain(|(id, type_id, _, _, kind)| {
+            let dir_type = match kind {
+                Kind::Asset => return true,
+                Kind::Dir => *type_id,
+                Kind::RecursiveDir(dir_type) | Kind::Query(dir_type, _, _) => *dir_type,
+            };
+            !(**id == *key.id() && dir_type == key.type_id())
+        });
+        self.removed_dirs.push(key);
     }
 
     #[inline]
     pub fn clear(&mut self) {
         self.added.clear();
+        self.removed_assets.clear();
+        self.removed_dirs.clear();
         self.cleared = true;
     }
 }
 
-struct WatchedPath<T> {
-    id: Box<str>,
-    types: Types<T>,
-}
-
-impl<T> WatchedPath<T> {
-    const fn new(id: Box<str>) -> Self {
-        Self {
-            id,
-            types: Types::new(),
-        }
+/// Records the result of reloading an asset, so that only the latest one is
+/// kept for each key.
+fn record(changed: &mut HashMap<Key, Loaded>, failed: &mut HashMap<Key, ReloadError>, key: Key, result: Result<Loaded, ReloadError>) {
+    match result {
+        Ok(asset) => {
+            failed.remove(&key);
+            changed.insert(key, asset);
+        },
+        Err(err) => {
+            changed.remove(&key);
+            failed.insert(key, err);
+        },
     }
 }
 
 enum Action {
     Added,
     Removed,
+    RemovedDir,
 }
 
-pub struct FileCache {
-    assets: HashMap<PathBuf, WatchedPath<LoadFn>>,
-    dirs: HashMap<PathBuf, WatchedPath<(LoadFn, Ext)>>,
+struct Query {
+    key: Key,
+    type_id: TypeId,
+    load: LoadFn,
+    ext: Ext,
+    matcher: Matcher,
+    /// The directory that contains all assets of the query
+    root: Box<str>,
+}
+
+pub(crate) struct FileCache {
+    assets: HashMap<Box<str>, Types<(LoadFn, Ext)>>,
+    dirs: HashMap<Box<str>, Types<(LoadFn, Ext)>>,
+    recursive_dirs: HashMap<Box<str>, Types<(LoadFn, Ext, TypeId)>>,
+    queries: Vec<Query>,
 
-    changed: HashMap<Key, Box<dyn AnyAsset>>,
+    changed: HashMap<Key, Loaded>,
     changed_dirs: Vec<(Key, Box<str>, Action)>,
+    failed: HashMap<Key, ReloadError>,
 }
 
 impl FileCache {
@@ -158,127 +263,314 @@ impl FileCache {
         Self {
             assets: HashMap::new(),
             dirs: HashMap::new(),
+            recursive_dirs: HashMap::new(),
+            queries: Vec::new(),
 
             changed: HashMap::new(),
             changed_dirs: Vec::new(),
+            failed: HashMap::new(),
         }
     }
 
-    pub fn load(&mut self, path: PathBuf) -> Option<()> {
-        let file_ext = extension_of(&path)?;
-
-        self.load_dir(&path, file_ext)?;
-        self.load_asset(&path, file_ext);
-
-        Some(())
+    pub fn handle(&mut self, event: Event, source: &dyn Source) {
+        match event {
+            Event::Changed { id, ext } => {
+                self.load_dir(&id, &ext);
+                self.load_asset(&id, &ext, source);
+            },
+            Event::Removed { id, ext } => self.remove(&id, &ext, source),
+        }
     }
 
-    fn load_asset(&mut self, path: &Path, file_ext: &str) {
-        if let Some(path_infos) = self.assets.get(path) {
-            let content = fs::read(path);
-
-            for (type_id, load) in &path_infos.types.0 {
-                if let Some(asset) = load(borrowed(&content), file_ext, &path_infos.id, path) {
-                    let key = Key::new_with(path_infos.id.clone(), *type_id);
-                    self.changed.insert(key, asset);
+    fn load_asset(&mut self, id: &str, file_ext: &str, source: &dyn Source) {
+        if let Some(types) = self.assets.get(id) {
+            for &(type_id, (load, type_ext)) in &types.0 {
+                if type_ext.contains(&file_ext) {
+                    let key = Key::new_with(id.into(), type_id);
+                    record(&mut self.changed, &mut self.failed, key, load(source, id, file_ext));
                 }
             }
         }
     }
 
-    fn load_dir(&mut self, path: &Path, file_ext: &str) -> Option<()> {
-        let parent = path.parent()?;
-        let file_stem = path.file_stem()?.to_str()?;
+    fn load_dir(&mut self, id: &str, file_ext: &str) {
+        let (dir_id, _) = split_id(id);
 
-        if let Some(path_infos) = self.dirs.get(parent) {
-            for &(type_id, (load, type_ext)) in &path_infos.types.0 {
+        if let Some(types) = self.dirs.get(dir_id) {
+            for &(type_id, (load, type_ext)) in &types.0 {
                 if type_ext.contains(&file_ext) {
-                    let key = Key::new_with(path_infos.id.clone(), type_id);
-                    let file_id = clone_and_push(&path_infos.id, file_stem);
+                    let key = Key::new_with(dir_id.into(), type_id);
+
+                    let watched = self.assets.entry(id.into()).or_insert_with(Types::new);
+                    watched.insert(type_id, (load, type_ext));
+
+                    self.changed_dirs.push((key, id.into(), Action::Added));
+                }
+            }
+        }
 
-                    let watched = self.assets.entry(path.into()).or_insert_with(|| WatchedPath::new(file_id.clone()));
-                    watched.types.insert(type_id, load);
+        for dir_id in ancestors(id) {
+            if let Some(types) = self.recursive_dirs.get(dir_id) {
+                for &(type_id, (load, type_ext, dir_type)) in &types.0 {
+                    if type_ext.contains(&file_ext) {
+                        let key = Key::new_with(dir_id.into(), dir_type);
 
-                    self.changed_dirs.push((key, file_id, Action::Added));
+                        let watched = self.assets.entry(id.into()).or_insert_with(Types::new);
+                        watched.insert(type_id, (load, type_ext));
+
+                        self.changed_dirs.push((key, id.into(), Action::Added));
+                    }
                 }
             }
         }
 
-        Some(())
+        for query in &self.queries {
+            if query.ext.contains(&file_ext) && (query.matcher)(id) {
+                let watched = self.assets.entry(id.into()).or_insert_with(Types::new);
+                watched.insert(query.type_id, (query.load, query.ext));
+
+                self.changed_dirs.push((query.key.clone(), id.into(), Action::Added));
+            }
+        }
     }
 
-    pub fn remove(&mut self, path: PathBuf) -> Option<()> {
-        let parent = path.parent()?;
-        let path_infos = self.dirs.get(parent)?;
-        let file_ext = extension_of(&path)?;
+    fn remove(&mut self, id: &str, file_ext: &str, source: &dyn Source) {
+        let (dir_id, _) = split_id(id);
 
-        let file_stem = path.file_stem()?.to_str()?;
+        if let Some(types) = self.dirs.get(dir_id) {
+            for &(type_id, (_, type_ext)) in &types.0 {
+                if !type_ext.contains(&file_ext) {
+                    continue;
+                }
 
-        for &(type_id, (_, type_ext)) in &path_infos.types.0 {
-            if type_ext.contains(&file_ext) {
-                let key = Key::new_with(path_infos.id.clone(), type_id);
-                let id = clone_and_push(&path_infos.id, file_stem);
-                self.changed_dirs.push((key, id, Action::Removed));
+                // The asset may still be available with another extension
+                if !exists_with(source, id, type_ext) {
+                    let key = Key::new_with(dir_id.into(), type_id);
+                    self.changed_dirs.push((key, id.into(), Action::Removed));
+                }
             }
         }
 
-        Some(())
-    }
+        let is_dir = source.exists(DirEntry::Directory(id));
+        for dir_id in ancestors(id) {
+            if let Some(types) = self.recursive_dirs.get(dir_id) {
+                for &(_, (_, type_ext, dir_type)) in &types.0 {
+                    let key = Key::new_with(dir_id.into(), dir_type);
+
+                    if type_ext.contains(&file_ext) && !exists_with(source, id, type_ext) {
+                        self.changed_dirs.push((key.clone(), id.into(), Action::Removed));
+                    }
 
-    pub fn update(&mut self, cache: &AssetCache) {
-        let mut assets = cache.assets.write();
+                    // The removed entry may have been a whole subdirectory
+                    if !is_dir {
+                        self.changed_dirs.push((key, id.into(), Action::RemovedDir));
+                    }
+                }
+            }
+        }
 
-        for (key, value) in self.changed.drain() {
-            log::info!("Reloading {:?}", key.id());
+        for query in &self.queries {
+            if query.ext.contains(&file_ext) && (query.matcher)(id) && !exists_with(source, id, query.ext) {
+                self.changed_dirs.push((query.key.clone(), id.into(), Action::Removed));
+            }
 
-            use std::collections::hash_map::Entry::*;
-            match assets.entry(key) {
-                Occupied(entry) => unsafe { value.reload(entry.get()) },
-                Vacant(entry) =>  { entry.insert(value.create()); },
+            let root = &*query.root;
+            if !is_dir && (is_in_dir(root, id) || root == id || is_in_dir(id, root)) {
+                self.changed_dirs.push((query.key.clone(), id.into(), Action::RemovedDir));
             }
+        }
 
+        // If the asset is still available (eg with another extension or in
+        // another layer of an `Overlay`), we reload it from there
+        if let Some(types) = self.assets.get(id) {
+            for &(type_id, (load, type_ext)) in &types.0 {
+                if type_ext.contains(&file_ext) && exists_with(source, id, type_ext) {
+                    let key = Key::new_with(id.into(), type_id);
+                    record(&mut self.changed, &mut self.failed, key, load(source, id, file_ext));
+                }
+            }
+        }
+    }
+
+    /// Applies changes to the cache, and returns what has changed.
+    ///
+    /// Reloaded assets, errors and changes of directories are added to
+    /// `report`.
+    ///
+    /// If `wait` is `false`, changes to assets and directories that are
+    /// currently locked are not applied but kept for the next call, so this
+    /// never blocks on a guard held by the user.
+    pub fn update<S: Source>(&mut self, cache: &AssetCache<S>, report: &mut ReloadReport, wait: bool) -> HashSet<Dependency> {
+        let mut changed = HashSet::new();
+        let mut subscribers = cache.subscribers.lock();
+
+        report.failed.extend(self.failed.drain().map(|(_, err)| err));
+
+        let mut locked = Vec::new();
+        for (key, (value, size)) in self.changed.drain() {
+            let type_name = value.type_name();
+
+            let mut assets = cache.assets.write(&key);
+            match assets.get(&key) {
+                Some(entry) => {
+                    if let Err(value) = unsafe { value.reload(entry, wait) } {
+                        locked.push((key, (value, size)));
+                        continue;
+                    }
+                    cache.budget.resize(entry, size);
+                },
+                None => {
+                    let entry = value.create();
+                    entry.set_size(size);
+                    entry.touch(cache.budget.tick());
+                    cache.budget.add(&entry);
+                    assets.insert(key.clone(), entry);
+                },
+            }
+            drop(assets);
+
+            log::info!("Reloading {:?}", key.id());
+            let reloaded = Reloaded {
+                id: key.id().into(),
+                type_name,
+            };
+            if !subscribers.is_empty() {
+                subscribers.notify_asset(&key, Change::Asset(reloaded.clone()));
+            }
+            report.reloaded.push(reloaded);
+            changed.insert(Dependency::Asset(key));
+        }
+        self.changed.extend(locked);
+
+        // Evicted assets are not reloaded anymore, as they would be put back
+        // in the cache
+        for (key, entry) in cache.budget.evict(&cache.assets, None) {
+            cache.compounds.lock().remove(&key);
+            self.forget_asset(&key);
+            cache.retire(entry);
         }
-        drop(assets);
 
         let dirs = cache.dirs.read();
 
+        // Changes of a directory have to be applied in order, so once one of
+        // them is delayed, the following ones are too
+        let mut locked = Vec::new();
+        let mut locked_dirs = HashSet::new();
         for (key, id, action) in self.changed_dirs.drain(..) {
-            match action {
+            let dir = match dirs.get(&key) {
+                Some(dir) => dir,
+                None => continue,
+            };
+            let ids = if locked_dirs.contains(&key) { None } else { dir.write_ids(wait) };
+            let mut ids = match ids {
+                Some(ids) => ids,
+                None => {
+                    locked_dirs.insert(key.clone());
+                    locked.push((key, id, action));
+                    continue;
+                },
+            };
+
+            let kind = match action {
                 Action::Added => {
-                    if let Some(dir) = dirs.get(&key) {
-                        if !dir.contains(&id) {
-                            log::info!("Adding {:?} to {:?}", id, key.id());
-                            dir.add(id);
-                        }
+                    if !ids.add(id.clone()) {
+                        continue;
                     }
-                }
+                    log::info!("Adding {:?} to {:?}", id, key.id());
+                    DirChangeKind::Added
+                },
                 Action::Removed => {
-                    if let Some(dir) = dirs.get(&key) {
-                        log::info!("Removing {:?} from {:?}", id, key.id());
-                        dir.remove(&id);
+                    if !ids.remove(&id) {
+                        continue;
                     }
-                }
+                    log::info!("Removing {:?} from {:?}", id, key.id());
+                    DirChangeKind::Removed
+                },
+                Action::RemovedDir => {
+                    if !ids.remove_dir(&id) {
+                        continue;
+                    }
+                    log::info!("Removing {:?} from {:?}", id, key.id());
+                    DirChangeKind::RemovedDir
+                },
+            };
+            drop(ids);
+
+            let dir_change = DirChange {
+                dir: key.id().into(),
+                id: id.into(),
+                kind,
+            };
+            if !subscribers.is_empty() {
+                subscribers.notify_dir(&key, Change::Dir(dir_change.clone()));
             }
+            report.dirs.push(dir_change);
+            changed.insert(Dependency::Dir(key));
         }
+        self.changed_dirs.extend(locked);
+
+        changed
     }
 
-    pub fn get_watched(&mut self, watched: &mut WatchedPaths) {
+    /// Stops watching an asset that was removed from the cache.
+    fn forget_asset(&mut self, key: &Key) {
+        retain_types(&mut self.assets, key.id(), |type_id, _| type_id != key.type_id());
+        self.changed.remove(key);
+        self.failed.remove(key);
+    }
+
+    /// Takes the paths added to and removed from the cache, and tells the
+    /// watcher about added ones.
+    pub fn get_watched(&mut self, watched: &mut WatchedPaths, watcher: &mut dyn Watcher) {
         if watched.cleared {
             watched.cleared = false;
-            watched.added.clear();
+            self.assets.clear();
+            self.dirs.clear();
+            self.recursive_dirs.clear();
+            self.queries.clear();
+            self.changed.clear();
+            self.changed_dirs.clear();
+            self.failed.clear();
+        }
+
+        for key in watched.removed_assets.drain(..) {
+            self.forget_asset(&key);
+        }
+
+        for key in watched.removed_dirs.drain(..) {
+            retain_types(&mut self.dirs, key.id(), |type_id, _| type_id != key.type_id());
+            retain_types(&mut self.recursive_dirs, key.id(), |_, &(_, _, dir_type)| dir_type != key.type_id());
+            self.queries.retain(|q| q.key != key);
+            self.changed_dirs.retain(|(k, _, _)| *k != key);
         }
 
-        for (path, id, type_id, load, kind) in watched.added.drain(..) {
-            match kind {
-                Kind::Asset => {
-                    let watched = self.assets.entry(path).or_insert_with(|| WatchedPath::new(id));
-                    watched.types.insert(type_id, load);
+        for (id, type_id, load, ext, kind) in watched.added.drain(..) {
+            match &kind {
+                Kind::Asset => ext.iter().for_each(|ext| watcher.add_entry(DirEntry::File(&id, ext), false)),
+                Kind::Dir => watcher.add_entry(DirEntry::Directory(&id), false),
+                Kind::RecursiveDir(_) => watcher.add_entry(DirEntry::Directory(&id), true),
+                Kind::Query(_, _, root) => watcher.add_entry(DirEntry::Directory(root), true),
+            }
+
+            let map = match kind {
+                Kind::Asset => &mut self.assets,
+                Kind::Dir => &mut self.dirs,
+                Kind::RecursiveDir(dir_type) => {
+                    let watched = self.recursive_dirs.entry(id).or_insert_with(Types::new);
+                    watched.insert(type_id, (load, ext, dir_type));
+                    continue;
                 },
-                Kind::Dir(ext) => {
-                    let watched = self.dirs.entry(path).or_insert_with(|| WatchedPath::new(id));
-                    watched.types.insert(type_id, (load, ext));
+                Kind::Query(dir_type, matcher, root) => {
+                    let key = Key::new_with(id, dir_type);
+                    if !self.queries.iter().any(|q| q.key == key) {
+                        self.queries.push(Query { key, type_id, load, ext, matcher, root });
+                    }
+                    continue;
                 },
-            }
+            };
+
+            let watched = map.entry(id).or_insert_with(Types::new);
+            watched.insert(type_id, (load, ext));
         }
     }
 }
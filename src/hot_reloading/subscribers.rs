@@ -0,0 +1,79 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::{
+    cache::Key,
+    utils::HashMap,
+};
+
+use super::Change;
+
+
+/// Sends a change to all senders, and forgets those whose receiver was
+/// dropped.
+fn send_all(senders: &mut Vec<Sender<Change>>, change: &Change) {
+    senders.retain(|sender| sender.send(change.clone()).is_ok());
+}
+
+/// Channels that are notified of changes made by hot-reloading.
+///
+/// Assets and directories are kept apart, because an asset and a directory
+/// can have the same key.
+pub(crate) struct Subscribers {
+    assets: HashMap<Key, Vec<Sender<Change>>>,
+    dirs: HashMap<Key, Vec<Sender<Change>>>,
+    all: Vec<Sender<Change>>,
+}
+
+impl Subscribers {
+    pub fn new() -> Self {
+        Self {
+            assets: HashMap::new(),
+            dirs: HashMap::new(),
+            all: Vec::new(),
+        }
+    }
+
+    pub fn subscribe_asset(&mut self, key: Key) -> Receiver<Change> {
+        let (sender, receiver) = channel();
+        self.assets.entry(key).or_default().push(sender);
+        receiver
+    }
+
+    pub fn subscribe_dir(&mut self, key: Key) -> Receiver<Change> {
+        let (sender, receiver) = channel();
+        self.dirs.entry(key).or_default().push(sender);
+        receiver
+    }
+
+    pub fn subscribe_all(&mut self) -> Receiver<Change> {
+        let (sender, receiver) = channel();
+        self.all.push(sender);
+        receiver
+    }
+
+    /// Returns `true` if there is no subscriber.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty() && self.dirs.is_empty() && self.all.is_empty()
+    }
+
+    /// Notifies the subscribers of an asset and global subscribers.
+    pub fn notify_asset(&mut self, key: &Key, change: Change) {
+        notify(&mut self.assets, &mut self.all, key, change);
+    }
+
+    /// Notifies the subscribers of a directory and global subscribers.
+    pub fn notify_dir(&mut self, key: &Key, change: Change) {
+        notify(&mut self.dirs, &mut self.all, key, change);
+    }
+}
+
+fn notify(map: &mut HashMap<Key, Vec<Sender<Change>>>, all: &mut Vec<Sender<Change>>, key: &Key, change: Change) {
+    if let Some(senders) = map.get_mut(key) {
+        send_all(senders, &change);
+        if senders.is_empty() {
+            map.remove(key);
+        }
+    }
+    send_all(all, &change);
+}
@@ -1,5 +1,6 @@
 use crate::{
     AssetCache,
+    source::{Memory, Overlay},
     tests::X,
 };
 use std::{
@@ -33,6 +34,31 @@ fn reload_asset() -> Res {
     reload_and_test(42, b"42")
 }
 
+#[test]
+fn memory_reload_asset() -> Res {
+    let source = Memory::new();
+    source.insert("a", "x", "1");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    let asset = cache.load::<X>("a")?;
+    cache.hot_reload();
+
+    source.insert("a", "x", "2");
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 2);
+
+    // Invalid content keeps the previous value
+    source.insert("a", "x", "error");
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 2);
+
+    source.insert("a", "x", "3");
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 3);
+
+    Ok(())
+}
+
 #[test]
 fn dir_remove_and_add() -> Res {
     let cache = AssetCache::new("assets")?;
@@ -60,3 +86,694 @@ fn dir_remove_and_add() -> Res {
 
     Ok(())
 }
+
+#[test]
+fn memory_dir_remove_and_add() -> Res {
+    let source = Memory::new();
+    source.insert("dir.a", "x", "61");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    let dir = cache.load_dir::<X>("dir")?;
+    cache.hot_reload();
+
+    let assert_value = |t: &[i32]| {
+        let mut res: Vec<_> = dir.iter().map(|x| x.read().0).collect();
+        res.sort();
+        assert_eq!(res, t);
+    };
+
+    assert_value(&[61]);
+
+    source.remove("dir.a", "x");
+    cache.hot_reload();
+    assert_value(&[]);
+
+    source.insert("dir.a", "x", "61");
+    source.insert("dir.b", "x", "7");
+    source.insert("dir.sub.c", "x", "8");
+    cache.hot_reload();
+    assert_value(&[7, 61]);
+
+    Ok(())
+}
+
+#[test]
+fn recursive_dir_remove_and_add() -> Res {
+    let source = Memory::new();
+    source.insert("dir.a", "x", "1");
+    source.insert("dir.sub.b", "x", "2");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    let dir = cache.load_dir_recursive::<X>("dir")?;
+    cache.hot_reload();
+
+    let assert_ids = |t: &[&str]| {
+        let ids: Vec<_> = dir.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, t);
+    };
+
+    assert_ids(&["dir.a", "dir.sub.b"]);
+
+    source.insert("dir.sub.new.c", "x", "3");
+    source.insert("other.d", "x", "4");
+    cache.hot_reload();
+    assert_ids(&["dir.a", "dir.sub.b", "dir.sub.new.c"]);
+    assert_eq!(cache.load_cached::<X>("dir.sub.new.c").unwrap().read().0, 3);
+
+    source.insert("dir.sub.new.c", "x", "5");
+    cache.hot_reload();
+    assert_eq!(cache.load_cached::<X>("dir.sub.new.c").unwrap().read().0, 5);
+
+    source.remove("dir.sub.b", "x");
+    cache.hot_reload();
+    assert_ids(&["dir.a", "dir.sub.new.c"]);
+
+    Ok(())
+}
+
+#[test]
+fn glob_remove_and_add() -> Res {
+    let source = Memory::new();
+    source.insert("ui.menu.icon_play", "x", "1");
+    source.insert("ui.menu.button", "x", "2");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    let icons = cache.load_glob::<X>("ui.*.icon_*")?;
+    let filtered = cache.load_filtered::<X, _>("ui", |id| id.ends_with("button"))?;
+    cache.hot_reload();
+
+    let ids = |dir: &crate::DirReader<X, Memory>| -> Vec<String> {
+        dir.iter_all().map(|(id, _)| id.to_owned()).collect()
+    };
+
+    assert_eq!(ids(&icons), ["ui.menu.icon_play"]);
+    assert_eq!(ids(&filtered), ["ui.menu.button"]);
+
+    source.insert("ui.options.icon_back", "x", "3");
+    source.insert("ui.options.sub.icon_no", "x", "4");
+    source.insert("ui.options.sub.button", "x", "5");
+    cache.hot_reload();
+    assert_eq!(ids(&icons), ["ui.menu.icon_play", "ui.options.icon_back"]);
+    assert_eq!(ids(&filtered), ["ui.menu.button", "ui.options.sub.button"]);
+    assert_eq!(cache.load_cached::<X>("ui.options.icon_back").unwrap().read().0, 3);
+
+    source.remove("ui.menu.icon_play", "x");
+    source.remove("ui.menu.button", "x");
+    cache.hot_reload();
+    assert_eq!(ids(&icons), ["ui.options.icon_back"]);
+    assert_eq!(ids(&filtered), ["ui.options.sub.button"]);
+
+    Ok(())
+}
+
+#[test]
+fn removals_outside_dirs() -> Res {
+    use crate::hot_reloading::{Change, DirChange, DirChangeKind};
+
+    let source = Memory::new();
+    source.insert("dir.a", "x", "1");
+    source.insert("dir.sub.b", "x", "2");
+    source.insert("dir.sub.c", "x", "3");
+    source.insert("ui.menu.icon_play", "x", "4");
+    source.insert("ui.menu.icon_stop", "x", "5");
+    source.insert("other.d", "x", "6");
+
+    let cache = AssetCache::with_source(source.clone())?;
+
+    // These files are removed before the directories are listed, so they are
+    // never part of them
+    source.remove("dir.sub.c", "x");
+    source.remove("ui.menu.icon_stop", "x");
+
+    cache.load_dir_recursive::<X>("dir")?;
+    cache.load_glob::<X>("ui.*.icon_*")?;
+    let all = cache.subscribe_all();
+    assert!(cache.hot_reload().dirs.is_empty());
+
+    source.remove("other.d", "x");
+    assert!(cache.hot_reload().dirs.is_empty());
+    assert!(all.try_recv().is_err());
+
+    source.remove("dir.sub.b", "x");
+    let removed = DirChange { dir: "dir".into(), id: "dir.sub.b".into(), kind: DirChangeKind::Removed };
+    assert_eq!(cache.hot_reload().dirs, vec![removed.clone()]);
+    assert_eq!(all.try_iter().collect::<Vec<_>>(), [Change::Dir(removed)]);
+
+    Ok(())
+}
+
+#[test]
+fn collected_assets_are_not_reloaded() -> Res {
+    let source = Memory::new();
+    source.insert("a", "x", "1");
+    source.insert("dir.b", "x", "2");
+
+    let mut cache = AssetCache::with_source(source.clone())?;
+    let handle = cache.load_handle::<X>("a")?;
+    cache.load_dir::<X>("dir")?;
+    cache.hot_reload();
+
+    drop(handle);
+    cache.collect_garbage();
+
+    source.insert("a", "x", "3");
+    source.insert("dir.b", "x", "4");
+    source.insert("dir.c", "x", "5");
+    cache.hot_reload();
+    assert!(cache.load_cached::<X>("a").is_none());
+    assert!(cache.load_cached::<X>("dir.b").is_none());
+    assert!(cache.load_cached::<X>("dir.c").is_none());
+
+    // Assets loaded again are watched again
+    let asset = cache.load::<X>("a")?;
+    assert_eq!(asset.read().0, 3);
+    source.insert("a", "x", "6");
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 6);
+
+    Ok(())
+}
+
+#[test]
+fn evicted_assets_are_not_reloaded() -> Res {
+    let source = Memory::new();
+    source.insert("a", "x", "1");
+    source.insert("b", "x", "2");
+    source.insert("c", "x", "33");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    cache.set_memory_budget(Some(3));
+    cache.load_handle::<X>("a")?;
+    cache.load_handle::<X>("b")?;
+    cache.hot_reload();
+
+    // Evicted when another asset is loaded
+    cache.load_handle::<X>("c")?;
+    assert!(cache.load_cached::<X>("a").is_none());
+    source.insert("a", "x", "4");
+    cache.hot_reload();
+    assert!(cache.load_cached::<X>("a").is_none());
+    assert_eq!(cache.memory_usage(), 3);
+
+    // Evicted when it grows on reload
+    source.insert("b", "x", "5555");
+    cache.hot_reload();
+    assert!(cache.load_cached::<X>("b").is_none());
+    source.insert("b", "x", "6");
+    cache.hot_reload();
+    assert!(cache.load_cached::<X>("b").is_none());
+    assert_eq!(cache.memory_usage(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn overlay_remove_and_add() -> Res {
+    let source = Overlay::from_dirs(&["assets/test/hot_overlay/base", "assets/test/hot_overlay/top"])?;
+    let cache = AssetCache::with_source(source)?;
+    let asset = cache.load::<X>("a")?;
+    cache.hot_reload();
+
+    assert_eq!(asset.read().0, 2);
+
+    fs::remove_file("assets/test/hot_overlay/top/a.x")?;
+    sleep();
+    cache.hot_reload();
+
+    assert_eq!(asset.read().0, 1);
+
+    File::create("assets/test/hot_overlay/top/a.x")?.write_all(b"2")?;
+    sleep();
+    cache.hot_reload();
+
+    assert_eq!(asset.read().0, 2);
+
+    Ok(())
+}
+
+#[test]
+fn memory_overlay_remove_and_add() -> Res {
+    let base = Memory::new();
+    let top = Memory::new();
+    base.insert("a", "x", "1");
+    top.insert("a", "x", "2");
+
+    let mut source = Overlay::new();
+    source.push(base);
+    source.push(top.clone());
+
+    let cache = AssetCache::with_source(source)?;
+    let asset = cache.load::<X>("a")?;
+    cache.hot_reload();
+
+    assert_eq!(asset.read().0, 2);
+
+    top.remove("a", "x");
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 1);
+
+    top.insert("a", "x", "2");
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 2);
+
+    Ok(())
+}
+
+#[test]
+fn cleared_cache() -> Res {
+    let source = Memory::new();
+    source.insert("a", "x", "1");
+
+    let mut cache = AssetCache::with_source(source.clone())?;
+    cache.load::<X>("a")?;
+    cache.hot_reload();
+
+    source.insert("a", "x", "2");
+    cache.clear();
+    cache.hot_reload();
+
+    assert!(cache.load_cached::<X>("a").is_none());
+
+    Ok(())
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[test]
+fn reload_compressed() -> Res {
+    use crate::tests::{TempDir, COMPRESSIONS, compress};
+
+    for ext in COMPRESSIONS {
+        let dir = TempDir::new();
+        let name = format!("a.x.{}", ext);
+        dir.write(&name, &compress(b"1", ext));
+
+        let cache = AssetCache::new(&dir.0)?;
+        let asset = cache.load::<X>("a")?;
+        cache.hot_reload();
+
+        dir.write(&name, &compress(b"2", ext));
+        sleep();
+        cache.hot_reload();
+        assert_eq!(asset.read().0, 2);
+
+        // The uncompressed file takes precedence
+        dir.write("a.x", b"3");
+        sleep();
+        cache.hot_reload();
+        assert_eq!(asset.read().0, 3);
+
+        std::fs::remove_file(dir.0.join("a.x"))?;
+        sleep();
+        cache.hot_reload();
+        assert_eq!(asset.read().0, 2);
+    }
+
+    Ok(())
+}
+
+mod compound {
+    use super::*;
+    use crate::{Compound, source::Source};
+
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Res = Result<(), Error>;
+
+    struct Sum(i32);
+
+    impl Compound for Sum {
+        fn load<S: Source>(cache: &AssetCache<S>, id: &str) -> Result<Sum, Error> {
+            let sum = cache.load_dir::<X>(id)?.iter().map(|x| x.read().0).sum();
+            Ok(Sum(sum))
+        }
+    }
+
+    /// Depends on another compound and on an asset.
+    struct Total(i32);
+
+    impl Compound for Total {
+        fn load<S: Source>(cache: &AssetCache<S>, id: &str) -> Result<Total, Error> {
+            let sum = cache.load_compound::<Sum>(&format!("{}.dir", id))?.read().0;
+            let extra = cache.load::<X>(&format!("{}.extra", id))?.read().0;
+            Ok(Total(sum + extra))
+        }
+    }
+
+    fn source() -> Memory {
+        let source = Memory::new();
+        source.insert("root.dir.a", "x", "1");
+        source.insert("root.dir.b", "x", "2");
+        source.insert("root.extra", "x", "10");
+        source
+    }
+
+    #[test]
+    fn subscribe() -> Res {
+        use crate::hot_reloading::{Change, Reloaded};
+
+        let source = source();
+        let cache = AssetCache::with_source(source.clone())?;
+
+        cache.load_compound::<Total>("root")?;
+        let total = cache.subscribe::<Total>("root");
+        cache.hot_reload();
+
+        source.insert("root.dir.a", "x", "5");
+        cache.hot_reload();
+        let type_name = std::any::type_name::<Total>();
+        assert_eq!(total.try_iter().collect::<Vec<_>>(), [Change::Asset(Reloaded { id: "root".into(), type_name })]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rebuild() -> Res {
+        let source = source();
+        let cache = AssetCache::with_source(source.clone())?;
+
+        let sum = cache.load_compound::<Sum>("root.dir")?;
+        let total = cache.load_compound::<Total>("root")?;
+        cache.hot_reload();
+        assert_eq!(sum.read().0, 3);
+        assert_eq!(total.read().0, 13);
+
+        // An asset of the directory changes
+        source.insert("root.dir.a", "x", "5");
+        cache.hot_reload();
+        assert_eq!(sum.read().0, 7);
+        assert_eq!(total.read().0, 17);
+
+        // An asset is added to the directory
+        source.insert("root.dir.c", "x", "3");
+        cache.hot_reload();
+        assert_eq!(sum.read().0, 10);
+        assert_eq!(total.read().0, 20);
+
+        // A direct dependency changes
+        source.insert("root.extra", "x", "100");
+        cache.hot_reload();
+        assert_eq!(sum.read().0, 10);
+        assert_eq!(total.read().0, 110);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_rebuild() -> Res {
+        let source = source();
+        let cache = AssetCache::with_source(source.clone())?;
+
+        let mut total = cache.load_compound::<Total>("root")?;
+        cache.hot_reload();
+
+        // An empty directory is still valid
+        source.remove("root.dir.a", "x");
+        source.remove("root.dir.b", "x");
+        cache.hot_reload();
+        assert_eq!(total.read().0, 10);
+        assert!(total.reloaded());
+
+        // A compound is left unchanged if it cannot be rebuilt
+        source.insert("root.extra", "x", "error");
+        cache.hot_reload();
+        assert_eq!(total.read().0, 10);
+        assert!(!total.reloaded());
+
+        Ok(())
+    }
+}
+
+#[test]
+fn report() -> Res {
+    use crate::hot_reloading::{DirChange, DirChangeKind, Reloaded};
+
+    let source = Memory::new();
+    source.insert("a", "x", "1");
+    source.insert("dir.a", "x", "1");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    cache.load::<X>("a")?;
+    cache.load_dir::<X>("dir")?;
+    assert!(cache.hot_reload().is_empty());
+
+    source.insert("a", "x", "2");
+    let report = cache.hot_reload();
+    let type_name = std::any::type_name::<X>();
+    assert_eq!(report.reloaded, [Reloaded { id: "a".into(), type_name }]);
+    assert!(report.failed.is_empty());
+    assert!(report.dirs.is_empty());
+
+    source.insert("a", "x", "error");
+    let report = cache.hot_reload();
+    assert!(report.reloaded.is_empty());
+    assert_eq!(report.failed.len(), 1);
+    let error = &report.failed[0];
+    assert_eq!((&*error.id, error.ext.as_deref(), error.type_name), ("a", Some("x"), type_name));
+    assert!(!error.message.is_empty());
+    assert_eq!(cache.load::<X>("a")?.read().0, 2);
+
+    source.insert("dir.b", "x", "3");
+    let report = cache.hot_reload();
+    assert_eq!(report.dirs, [DirChange { dir: "dir".into(), id: "dir.b".into(), kind: DirChangeKind::Added }]);
+    assert!(report.failed.is_empty());
+
+    source.remove("dir.b", "x");
+    let report = cache.hot_reload();
+    assert_eq!(report.dirs, [DirChange { dir: "dir".into(), id: "dir.b".into(), kind: DirChangeKind::Removed }]);
+
+    Ok(())
+}
+
+#[test]
+fn subscribe() -> Res {
+    use crate::hot_reloading::{Change, DirChange, DirChangeKind, Reloaded};
+
+    let source = Memory::new();
+    source.insert("a", "x", "1");
+    source.insert("b", "x", "1");
+    source.insert("dir.a", "x", "1");
+
+    let cache = AssetCache::with_source(source.clone())?;
+    cache.load::<X>("a")?;
+    cache.load::<X>("b")?;
+    cache.load_dir::<X>("dir")?;
+
+    let asset = cache.subscribe::<X>("a");
+    let dir = cache.subscribe_dir::<X>("dir");
+    let all = cache.subscribe_all();
+    cache.hot_reload();
+
+    let type_name = std::any::type_name::<X>();
+    let changed_a = Change::Asset(Reloaded { id: "a".into(), type_name });
+    let added = Change::Dir(DirChange { dir: "dir".into(), id: "dir.b".into(), kind: DirChangeKind::Added });
+
+    source.insert("a", "x", "2");
+    source.insert("b", "x", "2");
+    cache.hot_reload();
+    assert_eq!(asset.try_iter().collect::<Vec<_>>(), vec![changed_a.clone()]);
+    assert_eq!(all.try_iter().count(), 2);
+
+    // Failed reloads are not sent
+    source.insert("a", "x", "error");
+    cache.hot_reload();
+    assert!(asset.try_recv().is_err());
+
+    source.insert("dir.b", "x", "3");
+    cache.hot_reload();
+    assert_eq!(dir.try_iter().collect::<Vec<_>>(), vec![added.clone()]);
+    assert!(all.try_iter().any(|change| change == added));
+    assert!(asset.try_recv().is_err());
+
+    // Dropped receivers are forgotten
+    drop(all);
+    source.insert("a", "x", "3");
+    cache.hot_reload();
+    assert_eq!(asset.try_iter().collect::<Vec<_>>(), [changed_a]);
+
+    Ok(())
+}
+
+mod enhanced {
+    use super::*;
+
+    /// Waits until `f` returns `true`, or panics after a while.
+    fn wait_for(mut f: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if f() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("timed out");
+    }
+
+    fn cache(source: &Memory) -> &'static AssetCache<Memory> {
+        let cache = Box::leak(Box::new(AssetCache::with_source(source.clone()).unwrap()));
+        cache.enhance_hot_reloading();
+        cache
+    }
+
+    #[test]
+    fn reload_asset() -> Res {
+        let source = Memory::new();
+        source.insert("a", "x", "1");
+        source.insert("dir.a", "x", "1");
+
+        let cache = cache(&source);
+        let asset = cache.load::<X>("a")?;
+        let dir = cache.load_dir::<X>("dir")?;
+
+        source.insert("a", "x", "2");
+        wait_for(|| asset.read().0 == 2);
+
+        source.insert("dir.b", "x", "2");
+        wait_for(|| dir.iter().count() == 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn locked_asset() -> Res {
+        let source = Memory::new();
+        source.insert("a", "x", "1");
+
+        let cache = cache(&source);
+        let mut asset = cache.load::<X>("a")?;
+
+        // The thread does not wait for the guard to be dropped
+        let guard = asset.read();
+        source.insert("a", "x", "2");
+        sleep();
+        assert_eq!(guard.0, 1);
+        drop(guard);
+
+        wait_for(|| asset.reloaded());
+        assert_eq!(asset.read().0, 2);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn polling() -> Res {
+    use crate::{hot_reloading::HotReloadConfig, source::FileSystem, tests::TempDir};
+
+    let dir = TempDir::new();
+    dir.write("a.x", b"1");
+    std::fs::create_dir(dir.0.join("dir"))?;
+    dir.write("dir/a.x", b"1");
+
+    let config = HotReloadConfig::new().with_polling(Duration::from_millis(20));
+    let cache = AssetCache::with_config(FileSystem::new(&dir.0)?, &config)?;
+    let asset = cache.load::<X>("a")?;
+    let assets = cache.load_dir::<X>("dir")?;
+    cache.hot_reload();
+
+    // Sizes change so that changes are detected even if the modification
+    // time has a coarse resolution
+    dir.write("a.x", b"22");
+    dir.write("dir/b.x", b"2");
+    sleep();
+    cache.hot_reload();
+    assert_eq!(asset.read().0, 22);
+    assert_eq!(assets.iter().count(), 2);
+
+    std::fs::remove_file(dir.0.join("dir/b.x"))?;
+    sleep();
+    cache.hot_reload();
+    assert_eq!(assets.iter().count(), 1);
+
+    Ok(())
+}
+
+mod config {
+    use super::*;
+    use crate::hot_reloading::HotReloadConfig;
+
+    #[test]
+    fn is_watched() {
+        let config = HotReloadConfig::new()
+            .with_ignored("*.swp")
+            .with_ignored("*~")
+            .with_ignored("*.tmp");
+
+        assert!(config.is_watched("a", "x"));
+        assert!(config.is_watched("dir.a", "x"));
+        assert!(!config.is_watched("dir.a", "swp"));
+        assert!(!config.is_watched("dir.a", "x~"));
+        assert!(!config.is_watched("a~", ""));
+        assert!(!config.is_watched("dir.a", "tmp"));
+
+        let config = config.with_dir("dir").with_dir("other.dir");
+        assert!(!config.is_watched("a", "x"));
+        assert!(!config.is_watched("directory.a", "x"));
+        assert!(config.is_watched("dir.a", "x"));
+        assert!(config.is_watched("dir.sub.a", "x"));
+        assert!(config.is_watched("other.dir.a", "x"));
+        assert!(!config.is_watched("dir.a", "swp"));
+    }
+
+    #[test]
+    fn ignored() -> Res {
+        let source = Memory::new();
+        source.insert("a", "x", "1");
+        source.insert("b", "x", "1");
+
+        let config = HotReloadConfig::new().with_ignored("b.*");
+        let cache = AssetCache::with_config(source.clone(), &config)?;
+        let a = cache.load::<X>("a")?;
+        let b = cache.load::<X>("b")?;
+        cache.hot_reload();
+
+        source.insert("a", "x", "2");
+        source.insert("b", "x", "2");
+        cache.hot_reload();
+        assert_eq!(a.read().0, 2);
+        assert_eq!(b.read().0, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn watched_dirs() -> Res {
+        let source = Memory::new();
+        source.insert("a", "x", "1");
+        source.insert("dir.a", "x", "1");
+
+        let config = HotReloadConfig::new().with_dir("dir");
+        let cache = AssetCache::with_config(source.clone(), &config)?;
+        let a = cache.load::<X>("a")?;
+        let dir = cache.load_dir::<X>("dir")?;
+        cache.hot_reload();
+
+        source.insert("a", "x", "2");
+        source.insert("dir.a", "x", "2");
+        source.insert("dir.b", "x", "2");
+        cache.hot_reload();
+        assert_eq!(a.read().0, 1);
+        assert_eq!(cache.load::<X>("dir.a")?.read().0, 2);
+        assert_eq!(dir.iter().count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_watched_dir() -> Res {
+        use crate::{source::FileSystem, tests::TempDir};
+
+        let dir = TempDir::new();
+        let config = HotReloadConfig::new().with_dir("dir");
+        let cache = AssetCache::with_config(FileSystem::new(&dir.0)?, &config)?;
+
+        fs::create_dir(dir.0.join("dir"))?;
+        dir.write("dir/a.x", b"1");
+        let asset = cache.load::<X>("dir.a")?;
+        cache.hot_reload();
+
+        dir.write("dir/a.x", b"2");
+        sleep();
+        cache.hot_reload();
+        assert_eq!(asset.read().0, 2);
+
+        Ok(())
+    }
+}
@@ -3,13 +3,34 @@
 //! This crate aims at providing a filesystem abstraction to easily load external resources.
 //! It was originally thought for games, but can of course be used in other contexts.
 //!
-//! The structure [`AssetCache`] is the entry point of the crate.
+//! The structure [`AssetCache`] is the entry point of the crate. By default,
+//! it loads assets from the filesystem, but it can load them from any
+//! [`Source`].
 //!
 //! [`AssetCache`]: struct.AssetCache.html
+//! [`Source`]: source/trait.Source.html
 //!
 //! ## Cargo features
 //!
 //! - `hot-reloading`: Add hot-reloading
+//! - `rayon`: Load directories in parallel with [`AssetCache::load_dir_par`]
+//!
+//! [`AssetCache::load_dir_par`]: struct.AssetCache.html#method.load_dir_par
+//!
+//! ### Additionnal sources
+//! - `embedded`: Embed assets in the binary
+//! - `mmap`: Read files from the filesystem with memory maps
+//! - `zip`: Load assets from a zip archive
+//!
+//! ### Compressed files
+//!
+//! These features enable transparent decompression of files by the default
+//! source, see [`FileSystem`] for details.
+//!
+//! - `gzip`: Read `.gz` files
+//! - `zstd`: Read `.zst` files
+//!
+//! [`FileSystem`]: source/struct.FileSystem.html
 //!
 //! ### Additionnal loaders
 //! - `bincode`: Bincode deserialization
@@ -25,9 +46,13 @@
 //! These features change inner data structures implementations.
 //!
 //! - `parking_lot`: Use *parking_lot* crate's synchronisation primitives
+//! - `arc-swap`: Store assets in atomically swapped `Arc`s, so that reading an
+//!   asset never blocks reloading it, see [`AssetRef`] for details
 //! - `ahash`: Use ahash algorithm instead Sip1-3 used in `std`. This feature
 //!   is enabled by default.
 //!
+//! [`AssetRef`]: struct.AssetRef.html
+//!
 //! ## Example
 //!
 //! If the file `assets/common/position.ron` contains this:
@@ -95,19 +120,38 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+// Code generated by `embed!` refers to this crate by its name
+#[cfg(all(test, feature = "embedded"))]
+extern crate self as assets_manager;
+
 mod cache;
 pub use cache::{AssetCache, CacheError};
 
+mod asynchronous;
+
+mod budget;
+
+mod batch;
+pub use batch::{BatchProgress, LoadingBatch};
+
+mod compound;
+pub use compound::Compound;
+
 pub mod loader;
 
 mod entry;
-pub use entry::{AssetRef, AssetGuard};
+pub use entry::{AssetRef, AssetGuard, Handle};
+
+mod map;
 
 mod dirs;
 pub use dirs::{DirReader, ReadAllDir, ReadDir};
 
+pub mod source;
+
 #[cfg(feature = "hot-reloading")]
-mod hot_reloading;
+#[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+pub mod hot_reloading;
 
 mod utils;
 
@@ -113,7 +113,6 @@ mod tests;
 /// # let fruit = Ok(b" banana \n"[..].into());
 /// # assert_eq!(FruitLoader::load(fruit, "").unwrap(), Fruit::Banana);
 /// ```
-
 pub trait Loader<T> {
     /// The associated error which can be returned from loading.
     ///
@@ -284,7 +283,7 @@ macro_rules! serde_loader {
 
             #[inline]
             fn load(content: io::Result<Cow<[u8]>>, _: &str) -> Result<T, Self::Error> {
-                Ok($fun(&*content?).map_err(SerdeLoaderError::Serde)?)
+                $fun(&*content?).map_err(SerdeLoaderError::Serde)
             }
         }
 
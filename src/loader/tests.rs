@@ -3,7 +3,7 @@ use std::{borrow::Cow, io};
 use super::*;
 
 
-fn raw(s: &str) -> io::Result<Cow<[u8]>> {
+fn raw(s: &str) -> io::Result<Cow<'_, [u8]>> {
     Ok(s.as_bytes().into())
 }
 
@@ -0,0 +1,89 @@
+//! A sharded map to store cache entries
+
+use crate::{
+    cache::Key,
+    entry::CacheEntry,
+    utils::{HashMap, RandomState, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use std::{
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+/// The number of shards of a map, which must be a power of two.
+const SHARDS: usize = 64;
+
+type Shard = HashMap<Key, CacheEntry>;
+
+
+/// A map of cache entries, split into several shards so that threads that
+/// access different entries do not wait for each other.
+///
+/// The shard of an entry is chosen with the hash of its key, so `Key` and
+/// `AccessKey` can both be used to select it.
+pub(crate) struct AssetMap {
+    hasher: RandomState,
+    shards: Box<[RwLock<Shard>]>,
+}
+
+impl AssetMap {
+    pub fn new() -> Self {
+        Self {
+            hasher: RandomState::new(),
+            shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+        }
+    }
+
+    /// Returns the index of the shard that contains the given key.
+    #[inline]
+    #[allow(clippy::manual_hash_one)] // `hash_one` is too recent for our MSRV
+    fn index<K: Hash + ?Sized>(&self, key: &K) -> usize {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish() as usize & (SHARDS - 1)
+    }
+
+    #[inline]
+    fn shard<K: Hash + ?Sized>(&self, key: &K) -> &RwLock<Shard> {
+        &self.shards[self.index(key)]
+    }
+
+    /// Locks for reading the shard that contains the given key.
+    #[inline]
+    pub fn read<K: Hash + ?Sized>(&self, key: &K) -> RwLockReadGuard<'_, Shard> {
+        self.shard(key).read()
+    }
+
+    /// Locks for writing the shard that contains the given key.
+    #[inline]
+    pub fn write<K: Hash + ?Sized>(&self, key: &K) -> RwLockWriteGuard<'_, Shard> {
+        self.shard(key).write()
+    }
+
+    /// Gets the shard that contains the given key.
+    #[inline]
+    pub fn get_mut<K: Hash + ?Sized>(&mut self, key: &K) -> &mut Shard {
+        let index = self.index(key);
+        self.shards[index].get_mut()
+    }
+
+    /// Returns an iterator over all shards.
+    #[inline]
+    pub fn shards(&self) -> impl Iterator<Item = &RwLock<Shard>> {
+        self.shards.iter()
+    }
+
+    /// Returns an iterator over all shards.
+    #[inline]
+    pub fn shards_mut(&mut self) -> impl Iterator<Item = &mut Shard> {
+        self.shards.iter_mut().map(RwLock::get_mut)
+    }
+}
+
+impl fmt::Debug for AssetMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shards: Vec<_> = self.shards().map(RwLock::read).collect();
+        f.debug_map().entries(shards.iter().flat_map(|shard| shard.iter())).finish()
+    }
+}
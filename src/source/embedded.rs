@@ -0,0 +1,118 @@
+use std::{
+    fmt,
+    io,
+};
+
+use crate::utils::HashMap;
+use super::{DirEntry, FileContent, Source};
+
+
+/// The raw representation of embedded files.
+///
+/// This type is generated by the [`embed!`] macro, and is not meant to be
+/// created by hand.
+///
+/// [`embed!`]: macro.embed.html
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct RawEmbedded<'a> {
+    pub files: &'a [((&'a str, &'a str), &'a [u8])],
+    pub dirs: &'a [(&'a str, &'a [DirEntry<'a>])],
+}
+
+/// A [`Source`] which is embedded in the binary.
+///
+/// This source is created with the [`embed!`] macro, which takes a path to a
+/// directory relative to the root of the current crate (ie the directory that
+/// contains `Cargo.toml`). The whole directory tree is then included in the
+/// binary, and its files are given the same ids as a [`FileSystem`] would.
+///
+/// Files are embedded when the crate is compiled, so hot-reloading is not
+/// supported by this source. Note that Cargo only rebuilds the crate when an
+/// embedded file is modified, not when a file is added to the directory.
+///
+/// This type is only available with the `embedded` feature.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, source::{Embedded, embed}};
+/// # use assets_manager::{Asset, loader::{LoadFrom, ParseLoader}};
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+/// # impl Asset for X {
+/// #     const EXTENSION: &'static str = "x";
+/// #     type Loader = LoadFrom<i32, ParseLoader>;
+/// # }
+///
+/// let embedded: Embedded = embed!("assets");
+/// let cache = AssetCache::with_source(embedded)?;
+///
+/// // Loads the file `assets/test/cache.x`, without any I/O
+/// let x = cache.load::<X>("test.cache")?;
+/// assert_eq!(x.read().0, 42);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Source`]: trait.Source.html
+/// [`embed!`]: macro.embed.html
+/// [`FileSystem`]: struct.FileSystem.html
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded")))]
+pub struct Embedded<'a> {
+    files: HashMap<&'a str, Vec<(&'a str, &'a [u8])>>,
+    dirs: HashMap<&'a str, &'a [DirEntry<'a>]>,
+}
+
+impl<'a> From<RawEmbedded<'a>> for Embedded<'a> {
+    fn from(raw: RawEmbedded<'a>) -> Embedded<'a> {
+        let mut files: HashMap<&'a str, Vec<_>> = HashMap::new();
+        for &((id, ext), content) in raw.files {
+            files.entry(id).or_default().push((ext, content));
+        }
+
+        let mut dirs = HashMap::new();
+        for &(id, entries) in raw.dirs {
+            dirs.insert(id, entries);
+        }
+
+        Embedded {
+            files,
+            dirs,
+        }
+    }
+}
+
+impl<'a> Embedded<'a> {
+    fn get(&self, id: &str, ext: &str) -> Option<&'a [u8]> {
+        let exts = self.files.get(id)?;
+        exts.iter().find(|(e, _)| *e == ext).map(|&(_, content)| content)
+    }
+}
+
+impl Source for Embedded<'_> {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        match self.get(id, ext) {
+            Some(content) => Ok(FileContent::Slice(content)),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let dir = self.dirs.get(id).ok_or(io::ErrorKind::NotFound)?;
+        dir.iter().copied().for_each(f);
+        Ok(())
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        match entry {
+            DirEntry::File(id, ext) => self.get(id, ext).is_some(),
+            DirEntry::Directory(id) => self.dirs.contains_key(id),
+        }
+    }
+}
+
+impl fmt::Debug for Embedded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Embedded").field("dirs", &self.dirs).finish()
+    }
+}
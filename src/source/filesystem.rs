@@ -0,0 +1,550 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::dirs::{extension_of, id_push};
+
+#[cfg(feature = "hot-reloading")]
+use crate::dirs::id_of_relative_path;
+use super::{DirEntry, FileContent, Source};
+
+#[cfg(feature = "hot-reloading")]
+use crate::{
+    hot_reloading::{Event, HotReloadConfig, Watcher},
+    utils::HashMap,
+};
+#[cfg(feature = "hot-reloading")]
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant, SystemTime},
+};
+
+
+/// A compression format of files, which are transparently decompressed.
+struct Compression {
+    ext: &'static str,
+    decompress: fn(&[u8]) -> io::Result<Vec<u8>>,
+}
+
+/// Compression formats supported with the enabled features, in the order in
+/// which they are tried.
+const COMPRESSIONS: &[Compression] = &[
+    #[cfg(feature = "gzip")]
+    Compression { ext: "gz", decompress: gunzip },
+    #[cfg(feature = "zstd")]
+    Compression { ext: "zst", decompress: unzstd },
+];
+
+#[cfg(feature = "gzip")]
+fn gunzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut content = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+#[cfg(feature = "zstd")]
+fn unzstd(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::stream::decode_all(bytes)
+}
+
+/// Returns the path of the compressed variant of a file.
+fn compressed_path(path: &Path, compression: &Compression) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(compression.ext);
+    path.into()
+}
+
+/// If the path is the one of a compressed file, returns the path of the
+/// uncompressed file and the compression format.
+///
+/// A compressed file must have another extension, eg `foo.gz` is not
+/// considered to be compressed, but `foo.json.gz` is.
+fn uncompressed_path(path: &Path) -> Option<(PathBuf, &'static Compression)> {
+    let ext = path.extension()?;
+    let compression = COMPRESSIONS.iter().find(|c| *ext == *c.ext)?;
+
+    let uncompressed = path.with_extension("");
+    uncompressed.extension()?;
+    Some((uncompressed, compression))
+}
+
+/// Returns `true` if a compressed file is not the first variant of the
+/// uncompressed one to be read.
+fn is_shadowed(uncompressed: &Path, compression: &Compression) -> bool {
+    uncompressed.is_file() || COMPRESSIONS.iter()
+        .take_while(|c| c.ext != compression.ext)
+        .any(|c| compressed_path(uncompressed, c).is_file())
+}
+
+
+/// A [`Source`] to load assets from a directory in the file system.
+///
+/// This is the default source of an [`AssetCache`].
+///
+/// Within this source, ids are constructed from the path of files relative to
+/// the root, remplacing `/` by `.` and removing the extension.
+///
+/// With features `gzip` and `zstd`, compressed files are transparently
+/// decompressed: if file `foo.json` does not exist, `foo.json.gz` or
+/// `foo.json.zst` is read instead, with the same id and extension. These
+/// files are also taken into account for hot-reloading.
+///
+/// By default, hot-reloading relies on notifications of the operating system.
+/// When they are not available, for example on network file systems, use
+/// [`HotReloadConfig::with_polling`] to check files periodically instead.
+///
+/// [`Source`]: trait.Source.html
+/// [`AssetCache`]: ../struct.AssetCache.html
+/// [`HotReloadConfig::with_polling`]: ../hot_reloading/struct.HotReloadConfig.html#method.with_polling
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    path: PathBuf,
+
+    #[cfg(feature = "mmap")]
+    mmap: bool,
+}
+
+impl FileSystem {
+    /// Creates a new `FileSystem` from a directory.
+    ///
+    /// Assets will be searched in the directory given by `path`. Symbolic links
+    /// will be followed.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned if `path` is not valid readable directory.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<FileSystem> {
+        let path = path.as_ref().canonicalize()?;
+        let _ = path.read_dir()?;
+
+        Ok(FileSystem {
+            path,
+
+            #[cfg(feature = "mmap")]
+            mmap: false,
+        })
+    }
+
+    /// Makes this source read files with memory maps.
+    ///
+    /// With this option, the bytes given to loaders are borrowed from a
+    /// memory map of the file instead of being copied in a buffer. This is
+    /// useful for big files, especially with loaders that only borrow their
+    /// input.
+    ///
+    /// This function is only available with the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// A file must not be modified by another process while an asset is loaded
+    /// from it, or undefined behaviour may occur. Take care of this if you use
+    /// hot-reloading. See the documentation of `memmap2::Mmap::map` for more
+    /// details.
+    #[cfg(feature = "mmap")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mmap")))]
+    pub unsafe fn with_mmap(self) -> FileSystem {
+        FileSystem {
+            mmap: true,
+            ..self
+        }
+    }
+
+    /// Gets the path of the source's root.
+    ///
+    /// The path is currently given as absolute, but this may change in the future.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path that the given id and extension would have in the
+    /// file system.
+    pub fn path_of(&self, id: &str, ext: &str) -> PathBuf {
+        let mut path = self.dir_path_of(id);
+        path.set_extension(ext);
+        path
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<FileContent<'_>> {
+        #[cfg(feature = "mmap")]
+        if self.mmap {
+            let file = fs::File::open(path)?;
+            // Safety: This is guaranteed by the caller of `with_mmap`
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            return Ok(FileContent::Owned(Box::new(map)));
+        }
+
+        fs::read(path).map(Into::into)
+    }
+
+    fn dir_path_of(&self, id: &str) -> PathBuf {
+        let mut path = self.path.clone();
+        if !id.is_empty() {
+            path.extend(id.split('.'));
+        }
+        path
+    }
+
+    /// Returns the id and the extension of a file given its path, if it is
+    /// within the source's root.
+    #[cfg(feature = "hot-reloading")]
+    fn id_of(&self, path: &Path) -> Option<(String, String)> {
+        let relative = path.strip_prefix(&self.path).ok()?;
+        let uncompressed = uncompressed_path(relative).map(|(path, _)| path);
+        let relative = uncompressed.as_deref().unwrap_or(relative);
+
+        let (id, ext) = id_of_relative_path(relative)?;
+        Some((id, ext.to_owned()))
+    }
+}
+
+impl Source for FileSystem {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        let path = self.path_of(id, ext);
+
+        let err = match self.read_file(&path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => err,
+            result => return result,
+        };
+
+        if !ext.is_empty() {
+            for compression in COMPRESSIONS {
+                match fs::read(compressed_path(&path, compression)) {
+                    Ok(content) => return (compression.decompress)(&content).map(Into::into),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        Err(err)
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let dir_path = self.dir_path_of(id);
+        let entries = fs::read_dir(dir_path)?;
+
+        let mut entry_id = id.to_owned();
+
+        for entry in entries.flatten() {
+            let mut path = entry.path();
+
+            let is_file = path.is_file();
+            if is_file {
+                if let Some((uncompressed, compression)) = uncompressed_path(&path) {
+                    // Report files with several variants only once
+                    if is_shadowed(&uncompressed, compression) {
+                        continue;
+                    }
+                    path = uncompressed;
+                }
+            }
+
+            let name = match path.file_stem().and_then(|n| n.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let this_id: &str = {
+                entry_id.truncate(id.len());
+                id_push(&mut entry_id, name);
+                &entry_id
+            };
+
+            if is_file {
+                if let Some(ext) = extension_of(&path) {
+                    f(DirEntry::File(this_id, ext));
+                }
+            } else if path.is_dir() {
+                f(DirEntry::Directory(this_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        match entry {
+            DirEntry::File(id, ext) => {
+                let path = self.path_of(id, ext);
+                path.is_file() || (!ext.is_empty() && COMPRESSIONS.iter().any(|c| compressed_path(&path, c).is_file()))
+            },
+            DirEntry::Directory(id) => self.dir_path_of(id).is_dir(),
+        }
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    fn watch(&self) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        self.watch_with(&HotReloadConfig::default())
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    fn watch_with(&self, config: &HotReloadConfig) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(interval) = config.polling() {
+            return Ok(Some(Box::new(PollWatcher::new(self.clone(), interval))));
+        }
+
+        let watcher = FsWatcher::start(self.clone(), config)?;
+        Ok(Some(Box::new(watcher)))
+    }
+}
+
+
+/// Watches a `FileSystem` for changes with `notify`.
+#[cfg(feature = "hot-reloading")]
+struct FsWatcher {
+    source: FileSystem,
+    events: std::sync::mpsc::Receiver<notify::DebouncedEvent>,
+
+    _watcher: notify::RecommendedWatcher,
+}
+
+#[cfg(feature = "hot-reloading")]
+impl FsWatcher {
+    fn start(source: FileSystem, config: &HotReloadConfig) -> Result<Self, notify::Error> {
+        use notify::{RecursiveMode, Watcher as _};
+        use std::sync::mpsc::channel;
+
+        let (tx, events) = channel();
+
+        let mut watcher = notify::watcher(tx, config.debounce())?;
+        let dirs: Option<Vec<_>> = config.dirs()
+            .map(|dir| Some(source.dir_path_of(dir)).filter(|path| path.is_dir()))
+            .collect();
+        match dirs {
+            Some(dirs) if !dirs.is_empty() => {
+                for dir in dirs {
+                    watcher.watch(dir, RecursiveMode::Recursive)?;
+                }
+            },
+            // A watched directory may not exist yet, so the whole source is
+            // watched and events outside of watched directories are ignored
+            _ => watcher.watch(source.root(), RecursiveMode::Recursive)?,
+        }
+
+        Ok(FsWatcher {
+            source,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    fn changed(&self, path: &Path, f: &mut dyn FnMut(Event)) {
+        // A directory may be moved with its content, which does not trigger
+        // events for its files
+        if path.is_dir() {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    self.changed(&entry.path(), f);
+                }
+            }
+        } else if let Some((id, ext)) = self.source.id_of(path) {
+            f(Event::Changed { id, ext });
+        }
+    }
+
+    fn removed(&self, path: &Path, f: &mut dyn FnMut(Event)) {
+        if let Some((id, ext)) = self.source.id_of(path) {
+            f(Event::Removed { id, ext });
+        }
+    }
+}
+
+#[cfg(feature = "hot-reloading")]
+impl Watcher for FsWatcher {
+    fn source(&self) -> &dyn Source {
+        &self.source
+    }
+
+    fn poll(&mut self, f: &mut dyn FnMut(Event)) {
+        use notify::DebouncedEvent;
+
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                DebouncedEvent::Write(path)
+                | DebouncedEvent::Chmod(path)
+                | DebouncedEvent::Create(path) => self.changed(&path, f),
+                DebouncedEvent::Remove(path) => self.removed(&path, f),
+                DebouncedEvent::Rename(src, dst) => {
+                    self.changed(&dst, f);
+                    self.removed(&src, f);
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hot-reloading")]
+impl std::fmt::Debug for FsWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FsWatcher").field("root", &self.source.root()).finish()
+    }
+}
+
+
+/// The state of a file, used to detect changes when polling.
+#[cfg(feature = "hot-reloading")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Files found in the source, with their state.
+#[cfg(feature = "hot-reloading")]
+type Snapshot = HashMap<(String, String), Stamp>;
+
+/// Watches a `FileSystem` for changes by periodically checking the state of
+/// the entries used by the cache.
+#[cfg(feature = "hot-reloading")]
+struct PollWatcher {
+    source: FileSystem,
+    interval: Duration,
+    last_scan: Instant,
+
+    files: HashSet<(String, String)>,
+    /// Directories, and whether their subdirectories are watched too
+    dirs: HashMap<String, bool>,
+    snapshot: Snapshot,
+}
+
+#[cfg(feature = "hot-reloading")]
+impl PollWatcher {
+    fn new(source: FileSystem, interval: Duration) -> Self {
+        PollWatcher {
+            source,
+            interval,
+            last_scan: Instant::now(),
+
+            files: HashSet::new(),
+            dirs: HashMap::new(),
+            snapshot: HashMap::new(),
+        }
+    }
+
+    /// Returns the state of the file that would be read for the given id and
+    /// extension, if any.
+    fn stamp(&self, id: &str, ext: &str) -> Option<Stamp> {
+        let path = self.source.path_of(id, ext);
+        let compressed = COMPRESSIONS.iter()
+            .filter(|_| !ext.is_empty())
+            .map(|c| compressed_path(&path, c));
+
+        std::iter::once(path.clone()).chain(compressed)
+            .filter_map(|path| fs::metadata(path).ok())
+            .find(|meta| meta.is_file())
+            .map(|meta| Stamp {
+                modified: meta.modified().ok(),
+                len: meta.len(),
+            })
+    }
+
+    fn scan_file(&self, id: &str, ext: &str, snapshot: &mut Snapshot) {
+        if let Some(stamp) = self.stamp(id, ext) {
+            snapshot.insert((id.to_owned(), ext.to_owned()), stamp);
+        }
+    }
+
+    fn scan_dir(&self, id: &str, recursive: bool, snapshot: &mut Snapshot) {
+        let mut subdirs = Vec::new();
+
+        let _ = self.source.read_dir(id, &mut |entry| match entry {
+            DirEntry::File(id, ext) => self.scan_file(id, ext, snapshot),
+            DirEntry::Directory(id) => if recursive {
+                subdirs.push(id.to_owned());
+            },
+        });
+
+        for subdir in subdirs {
+            self.scan_dir(&subdir, true, snapshot);
+        }
+    }
+
+    fn scan(&self) -> Snapshot {
+        let mut snapshot = HashMap::new();
+
+        for (id, ext) in &self.files {
+            self.scan_file(id, ext, &mut snapshot);
+        }
+        for (id, &recursive) in self.dirs.iter() {
+            self.scan_dir(id, recursive, &mut snapshot);
+        }
+
+        snapshot
+    }
+}
+
+#[cfg(feature = "hot-reloading")]
+impl Watcher for PollWatcher {
+    fn source(&self) -> &dyn Source {
+        &self.source
+    }
+
+    fn poll(&mut self, f: &mut dyn FnMut(Event)) {
+        if self.last_scan.elapsed() < self.interval {
+            return;
+        }
+        self.last_scan = Instant::now();
+
+        let snapshot = self.scan();
+
+        for (key, stamp) in snapshot.iter() {
+            if self.snapshot.get(key) != Some(stamp) {
+                let (id, ext) = key.clone();
+                f(Event::Changed { id, ext });
+            }
+        }
+        for key in self.snapshot.keys() {
+            if !snapshot.contains_key(key) {
+                let (id, ext) = key.clone();
+                f(Event::Removed { id, ext });
+            }
+        }
+
+        self.snapshot = snapshot;
+    }
+
+    fn add_entry(&mut self, entry: DirEntry, recursive: bool) {
+        // The current state of new entries is recorded, so that only later
+        // changes are reported
+        let mut snapshot = HashMap::new();
+
+        match entry {
+            DirEntry::File(id, ext) => {
+                if self.files.insert((id.to_owned(), ext.to_owned())) {
+                    self.scan_file(id, ext, &mut snapshot);
+                }
+            },
+            DirEntry::Directory(id) => {
+                let is_new = match self.dirs.get(id) {
+                    None => true,
+                    Some(&was_recursive) => recursive && !was_recursive,
+                };
+                if is_new {
+                    self.dirs.insert(id.to_owned(), recursive);
+                    self.scan_dir(id, recursive, &mut snapshot);
+                }
+            },
+        }
+
+        for (key, stamp) in snapshot.drain() {
+            self.snapshot.entry(key).or_insert(stamp);
+        }
+    }
+}
+
+#[cfg(feature = "hot-reloading")]
+impl std::fmt::Debug for PollWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PollWatcher")
+            .field("root", &self.source.root())
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
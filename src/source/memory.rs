@@ -0,0 +1,249 @@
+use std::{
+    fmt,
+    io,
+    sync::Arc,
+};
+
+use crate::utils::{HashMap, RwLock};
+use super::{DirEntry, FileContent, Source};
+
+#[cfg(feature = "hot-reloading")]
+use crate::{
+    hot_reloading::{Event, Watcher},
+    utils::Mutex,
+};
+#[cfg(feature = "hot-reloading")]
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+
+/// Returns `true` if `id` is a direct child of directory `dir`.
+fn is_child_of(id: &str, dir: &str) -> bool {
+    match id.rfind('.') {
+        Some(pos) => &id[..pos] == dir,
+        None => dir.is_empty(),
+    }
+}
+
+/// Returns `true` if `id` is somewhere in directory `dir`.
+fn is_in(id: &str, dir: &str) -> bool {
+    if dir.is_empty() {
+        return true;
+    }
+    id.starts_with(dir) && id.as_bytes().get(dir.len()) == Some(&b'.')
+}
+
+/// A [`Source`] that stores its files in memory.
+///
+/// Files can be inserted, replaced and removed at any time, through any clone
+/// of the `Memory`, as all clones share the same files. Directories are not
+/// stored: a directory exists as long as it contains a file, and the root
+/// directory always exists.
+///
+/// This is mostly useful for tests. With hot-reloading, each change is
+/// reported to the [`AssetCache`] using this source, and is visible after the
+/// next call to [`hot_reload`], without any delay.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, source::Memory};
+/// # use assets_manager::{Asset, loader::{LoadFrom, ParseLoader}};
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+/// # impl Asset for X {
+/// #     const EXTENSION: &'static str = "x";
+/// #     type Loader = LoadFrom<i32, ParseLoader>;
+/// # }
+///
+/// let source = Memory::new();
+/// source.insert("example.a", "x", "42");
+///
+/// let cache = AssetCache::with_source(source)?;
+/// let x = cache.load::<X>("example.a")?;
+/// assert_eq!(x.read().0, 42);
+///
+/// // Files can also be modified through the cache
+/// cache.source().insert("example.a", "x", "17");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Source`]: trait.Source.html
+/// [`AssetCache`]: ../struct.AssetCache.html
+/// [`hot_reload`]: ../struct.AssetCache.html#method.hot_reload
+#[derive(Clone)]
+pub struct Memory {
+    inner: Arc<Inner>,
+}
+
+/// Content of files, by id and extension.
+type Files = HashMap<Box<str>, Vec<(Box<str>, Arc<[u8]>)>>;
+
+struct Inner {
+    files: RwLock<Files>,
+
+    #[cfg(feature = "hot-reloading")]
+    watchers: Mutex<Vec<Sender<Event>>>,
+}
+
+impl Memory {
+    /// Creates a `Memory` without any file.
+    pub fn new() -> Memory {
+        let inner = Inner {
+            files: RwLock::new(HashMap::new()),
+
+            #[cfg(feature = "hot-reloading")]
+            watchers: Mutex::new(Vec::new()),
+        };
+
+        Memory {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Adds a file with the given id and extension, replacing the previous
+    /// one if any.
+    pub fn insert(&self, id: &str, ext: &str, content: impl Into<Vec<u8>>) {
+        let content: Arc<[u8]> = content.into().into();
+
+        let mut files = self.inner.files.write();
+        let exts = files.entry(id.into()).or_default();
+        match exts.iter_mut().find(|(e, _)| &**e == ext) {
+            Some((_, old)) => *old = content,
+            None => exts.push((ext.into(), content)),
+        }
+        drop(files);
+
+        #[cfg(feature = "hot-reloading")]
+        self.notify(Event::Changed { id: id.into(), ext: ext.into() });
+    }
+
+    /// Removes a file, returning its content if it existed.
+    pub fn remove(&self, id: &str, ext: &str) -> Option<Vec<u8>> {
+        let mut files = self.inner.files.write();
+        let exts = files.get_mut(id)?;
+        let pos = exts.iter().position(|(e, _)| &**e == ext)?;
+        let (_, content) = exts.swap_remove(pos);
+        if exts.is_empty() {
+            files.remove(id);
+        }
+        drop(files);
+
+        #[cfg(feature = "hot-reloading")]
+        self.notify(Event::Removed { id: id.into(), ext: ext.into() });
+
+        Some(content.to_vec())
+    }
+
+    /// Returns `true` if the `Memory` has no file.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.files.read().is_empty()
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    fn notify(&self, event: Event) {
+        let mut watchers = self.inner.watchers.lock();
+        watchers.retain(|watcher| watcher.send(event.clone()).is_ok());
+    }
+
+    fn get(&self, id: &str, ext: &str) -> Option<Arc<[u8]>> {
+        let files = self.inner.files.read();
+        let exts = files.get(id)?;
+        exts.iter().find(|(e, _)| &**e == ext).map(|(_, content)| content.clone())
+    }
+}
+
+impl Default for Memory {
+    #[inline]
+    fn default() -> Memory {
+        Memory::new()
+    }
+}
+
+impl Source for Memory {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        match self.get(id, ext) {
+            Some(content) => Ok(FileContent::Owned(Box::new(content))),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        if !self.exists(DirEntry::Directory(id)) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let files = self.inner.files.read();
+        let mut dirs = Vec::new();
+
+        for (file_id, exts) in files.iter() {
+            if is_child_of(file_id, id) {
+                exts.iter().for_each(|(ext, _)| f(DirEntry::File(file_id, ext)));
+            } else if is_in(file_id, id) {
+                // Get the id of the subdirectory that contains the file
+                let start = if id.is_empty() { 0 } else { id.len() + 1 };
+                let end = file_id[start..].find('.').map_or(file_id.len(), |pos| start + pos);
+                let dir_id = &file_id[..end];
+
+                if !dirs.contains(&dir_id) {
+                    dirs.push(dir_id);
+                }
+            }
+        }
+
+        dirs.into_iter().for_each(|dir| f(DirEntry::Directory(dir)));
+        Ok(())
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        match entry {
+            DirEntry::File(id, ext) => self.get(id, ext).is_some(),
+            DirEntry::Directory(id) => {
+                id.is_empty() || self.inner.files.read().keys().any(|file_id| is_in(file_id, id))
+            },
+        }
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    fn watch(&self) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        let (tx, rx) = channel();
+        self.inner.watchers.lock().push(tx);
+
+        Ok(Some(Box::new(MemoryWatcher {
+            source: self.clone(),
+            events: rx,
+        })))
+    }
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files = self.inner.files.read();
+        let mut list = f.debug_list();
+        for (id, exts) in files.iter() {
+            for (ext, _) in exts {
+                list.entry(&format_args!("{}.{}", id, ext));
+            }
+        }
+        list.finish()
+    }
+}
+
+
+/// Receives the changes made to a `Memory`.
+#[cfg(feature = "hot-reloading")]
+struct MemoryWatcher {
+    source: Memory,
+    events: Receiver<Event>,
+}
+
+#[cfg(feature = "hot-reloading")]
+impl Watcher for MemoryWatcher {
+    fn source(&self) -> &dyn Source {
+        &self.source
+    }
+
+    fn poll(&mut self, f: &mut dyn FnMut(Event)) {
+        self.events.try_iter().for_each(f);
+    }
+}
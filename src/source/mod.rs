@@ -0,0 +1,385 @@
+//! Sources from which assets can be loaded.
+//!
+//! By default, an [`AssetCache`] loads its assets from the filesystem, using
+//! the [`FileSystem`] source. This module defines trait [`Source`], which
+//! enables you to load assets from anywhere else.
+//!
+//! [`AssetCache`]: ../struct.AssetCache.html
+//! [`FileSystem`]: struct.FileSystem.html
+//! [`Source`]: trait.Source.html
+
+use std::{
+    borrow::Cow,
+    fmt,
+    io,
+    ops::Deref,
+};
+
+#[cfg(feature = "hot-reloading")]
+use crate::hot_reloading::{HotReloadConfig, Watcher};
+
+mod filesystem;
+pub use filesystem::FileSystem;
+
+mod memory;
+pub use memory::Memory;
+
+mod overlay;
+pub use overlay::Overlay;
+
+mod pack;
+pub use pack::{Pack, PackBuilder};
+
+#[cfg(feature = "zip")]
+mod zip;
+#[cfg(feature = "zip")]
+pub use self::zip::Zip;
+
+#[cfg(feature = "embedded")]
+mod embedded;
+#[cfg(feature = "embedded")]
+pub use embedded::{Embedded, RawEmbedded};
+
+/// Embeds a directory in the binary.
+///
+/// See [`Embedded`] for more informations.
+///
+/// [`Embedded`]: struct.Embedded.html
+#[cfg(feature = "embedded")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded")))]
+pub use assets_manager_macros::embed;
+
+#[cfg(test)]
+mod tests;
+
+
+/// An entry of a source.
+///
+/// Ids are given as they are used by the [`AssetCache`], ie the id of a file
+/// contains the id of its parent directory.
+///
+/// [`AssetCache`]: ../struct.AssetCache.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DirEntry<'a> {
+    /// A file with an id and an extension.
+    File(&'a str, &'a str),
+
+    /// A directory with an id.
+    Directory(&'a str),
+}
+
+impl<'a> DirEntry<'a> {
+    /// Returns `true` if this is a `File`.
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        matches!(self, DirEntry::File(..))
+    }
+
+    /// Returns `true` if this is a `Directory`.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        matches!(self, DirEntry::Directory(_))
+    }
+
+    /// Returns the id of the entry.
+    #[inline]
+    pub fn id(self) -> &'a str {
+        match self {
+            DirEntry::File(id, _) => id,
+            DirEntry::Directory(id) => id,
+        }
+    }
+}
+
+
+/// The content of a file, as returned by a [`Source`].
+///
+/// This type enables sources to give bytes to loaders without copying them,
+/// even when these bytes are not owned by the source itself (eg with a memory
+/// map).
+///
+/// [`Source`]: trait.Source.html
+pub enum FileContent<'a> {
+    /// A slice borrowed from the source.
+    Slice(&'a [u8]),
+
+    /// An owned buffer.
+    Buffer(Vec<u8>),
+
+    /// Bytes owned by another value, which is dropped once the asset is
+    /// loaded.
+    Owned(Box<dyn AsRef<[u8]> + 'a>),
+}
+
+impl<'a> FileContent<'a> {
+    /// Calls a function with the content as a `Cow`, without copying it.
+    ///
+    /// This is how bytes are given to a [`Loader`].
+    ///
+    /// [`Loader`]: ../loader/trait.Loader.html
+    pub fn with_cow<R>(self, f: impl FnOnce(Cow<'_, [u8]>) -> R) -> R {
+        match self {
+            FileContent::Slice(bytes) => f(Cow::Borrowed(bytes)),
+            FileContent::Buffer(bytes) => f(Cow::Owned(bytes)),
+            FileContent::Owned(owned) => f(Cow::Borrowed((*owned).as_ref())),
+        }
+    }
+
+    /// Converts the content into an owned buffer, copying it if needed.
+    pub fn into_owned(self) -> Vec<u8> {
+        match self {
+            FileContent::Buffer(bytes) => bytes,
+            other => other.to_vec(),
+        }
+    }
+}
+
+impl Deref for FileContent<'_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileContent::Slice(bytes) => bytes,
+            FileContent::Buffer(bytes) => bytes,
+            FileContent::Owned(owned) => (**owned).as_ref(),
+        }
+    }
+}
+
+impl AsRef<[u8]> for FileContent<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<'a> From<&'a [u8]> for FileContent<'a> {
+    #[inline]
+    fn from(bytes: &'a [u8]) -> FileContent<'a> {
+        FileContent::Slice(bytes)
+    }
+}
+
+impl From<Vec<u8>> for FileContent<'_> {
+    #[inline]
+    fn from(bytes: Vec<u8>) -> Self {
+        FileContent::Buffer(bytes)
+    }
+}
+
+impl<'a> From<Cow<'a, [u8]>> for FileContent<'a> {
+    #[inline]
+    fn from(bytes: Cow<'a, [u8]>) -> FileContent<'a> {
+        match bytes {
+            Cow::Borrowed(bytes) => FileContent::Slice(bytes),
+            Cow::Owned(bytes) => FileContent::Buffer(bytes),
+        }
+    }
+}
+
+impl fmt::Debug for FileContent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            FileContent::Slice(_) => "Slice",
+            FileContent::Buffer(_) => "Buffer",
+            FileContent::Owned(_) => "Owned",
+        };
+        f.debug_tuple(kind).field(&&**self).finish()
+    }
+}
+
+
+/// Bytes sources to load assets from.
+///
+/// This trait provides an abstraction over a basic filesystem, which is used
+/// to load assets independently from the actual storage kind.
+///
+/// As a consumer of this library, you generally don't need to use this trait
+/// directly, except to give a source to [`AssetCache::with_source`].
+///
+/// # Example
+///
+/// A source that serves assets from a static list:
+///
+/// ```
+/// use assets_manager::{AssetCache, source::{DirEntry, FileContent, Source}};
+/// use std::io;
+/// # use assets_manager::{Asset, loader::{LoadFrom, ParseLoader}};
+///
+/// struct Static(&'static [(&'static str, &'static str, &'static [u8])]);
+///
+/// impl Source for Static {
+///     fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+///         self.0.iter()
+///             .find(|(i, e, _)| *i == id && *e == ext)
+///             .map(|(_, _, bytes)| FileContent::Slice(bytes))
+///             .ok_or_else(|| io::ErrorKind::NotFound.into())
+///     }
+///
+///     fn read_dir(&self, _: &str, _: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+///         Err(io::ErrorKind::NotFound.into())
+///     }
+///
+///     fn exists(&self, entry: DirEntry) -> bool {
+///         match entry {
+///             DirEntry::File(id, ext) => self.read(id, ext).is_ok(),
+///             DirEntry::Directory(_) => false,
+///         }
+///     }
+/// }
+///
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+/// # impl Asset for X {
+/// #     const EXTENSION: &'static str = "x";
+/// #     type Loader = LoadFrom<i32, ParseLoader>;
+/// # }
+/// let cache = AssetCache::with_source(Static(&[("answer", "x", b"42")]))?;
+/// assert_eq!(cache.load::<X>("answer")?.read().0, 42);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`AssetCache::with_source`]: ../struct.AssetCache.html#method.with_source
+pub trait Source {
+    /// Try reading the source given an id and an extension.
+    ///
+    /// If no error occurs, this function returns the raw bytes of the file,
+    /// which will be given to the asset's [`Loader`].
+    ///
+    /// [`Loader`]: ../loader/trait.Loader.html
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>>;
+
+    /// Reads a file of an asset that can have any of the extensions `exts`.
+    ///
+    /// This is used by the cache to load assets: `ext` is one of `exts`, and
+    /// extensions are tried in order. Sources made of several other sources
+    /// can use `exts` to read all files of an asset from the same source.
+    ///
+    /// The default implementation calls [`read`].
+    ///
+    /// [`read`]: #tymethod.read
+    fn read_asset(&self, id: &str, ext: &str, exts: &[&str]) -> io::Result<FileContent<'_>> {
+        let _ = exts;
+        self.read(id, ext)
+    }
+
+    /// Reads the content of a directory.
+    ///
+    /// If no error occurs, this function calls `f` with each entry of the
+    /// directory with the given id. The order of entries is not specified.
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()>;
+
+    /// Returns `true` if the entry points at an existing entity.
+    fn exists(&self, entry: DirEntry) -> bool;
+
+    /// Starts watching the source for changes, if supported.
+    ///
+    /// This function is called once when an [`AssetCache`] is created with
+    /// this source. The returned [`Watcher`] is then used by the cache to
+    /// reload changed assets.
+    ///
+    /// The default implementation returns `Ok(None)`, which means that the
+    /// source does not support hot-reloading.
+    ///
+    /// [`AssetCache`]: ../struct.AssetCache.html
+    /// [`Watcher`]: ../hot_reloading/trait.Watcher.html
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    fn watch(&self) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    /// Starts watching the source for changes with the given settings, if
+    /// supported.
+    ///
+    /// This is called instead of [`watch`] when a cache is created with
+    /// [`AssetCache::with_config`]. Sources can use the settings to configure
+    /// their watcher (eg to watch only some directories), but do not need to
+    /// filter events themselves: this is done by the cache.
+    ///
+    /// The default implementation calls [`watch`].
+    ///
+    /// [`watch`]: #method.watch
+    /// [`AssetCache::with_config`]: ../struct.AssetCache.html#method.with_config
+    #[cfg(feature = "hot-reloading")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hot-reloading")))]
+    fn watch_with(&self, config: &HotReloadConfig) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = config;
+        self.watch()
+    }
+}
+
+impl<S> Source for Box<S>
+where
+    S: Source + ?Sized,
+{
+    #[inline]
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        self.as_ref().read(id, ext)
+    }
+
+    #[inline]
+    fn read_asset(&self, id: &str, ext: &str, exts: &[&str]) -> io::Result<FileContent<'_>> {
+        self.as_ref().read_asset(id, ext, exts)
+    }
+
+    #[inline]
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        self.as_ref().read_dir(id, f)
+    }
+
+    #[inline]
+    fn exists(&self, entry: DirEntry) -> bool {
+        self.as_ref().exists(entry)
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn watch(&self) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        self.as_ref().watch()
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn watch_with(&self, config: &HotReloadConfig) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        self.as_ref().watch_with(config)
+    }
+}
+
+impl<S> Source for std::sync::Arc<S>
+where
+    S: Source + ?Sized,
+{
+    #[inline]
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        self.as_ref().read(id, ext)
+    }
+
+    #[inline]
+    fn read_asset(&self, id: &str, ext: &str, exts: &[&str]) -> io::Result<FileContent<'_>> {
+        self.as_ref().read_asset(id, ext, exts)
+    }
+
+    #[inline]
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        self.as_ref().read_dir(id, f)
+    }
+
+    #[inline]
+    fn exists(&self, entry: DirEntry) -> bool {
+        self.as_ref().exists(entry)
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn watch(&self) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        self.as_ref().watch()
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    #[inline]
+    fn watch_with(&self, config: &HotReloadConfig) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        self.as_ref().watch_with(config)
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    io,
+    path::Path,
+    sync::Arc,
+};
+
+use super::{DirEntry, FileContent, FileSystem, Source};
+
+#[cfg(feature = "hot-reloading")]
+use crate::hot_reloading::{Event, HotReloadConfig, Watcher};
+
+
+/// A [`Source`] made of a stack of other sources.
+///
+/// This is typically used to support mods or patches: each layer can override
+/// files of the layers below it.
+///
+/// - When a file is read, it is read from the highest layer that contains it.
+/// - When an [`Asset`] is loaded, it is read from the highest layer that
+///   contains it with any of its extensions, and its extensions are tried in
+///   order within this layer only.
+/// - When a directory is read, the entries of all layers are merged.
+///
+/// With hot-reloading, changes in any layer that supports it are taken into
+/// account. In particular, when a file is removed from a layer, the asset is
+/// reloaded from the next layer that contains it.
+///
+/// # Example
+///
+/// ```no_run
+/// use assets_manager::{AssetCache, source::Overlay};
+///
+/// // Files in "mods/my_mod" override those in "assets"
+/// let source = Overlay::from_dirs(&["assets", "mods/my_mod"])?;
+/// let cache = AssetCache::with_source(source)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Source`]: trait.Source.html
+/// [`Asset`]: ../trait.Asset.html
+#[derive(Clone, Default)]
+pub struct Overlay {
+    /// Layers, from the lowest to the highest.
+    layers: Vec<Arc<dyn Source + Send + Sync>>,
+}
+
+impl Overlay {
+    /// Creates an `Overlay` without any layer.
+    #[inline]
+    pub fn new() -> Overlay {
+        Overlay {
+            layers: Vec::new(),
+        }
+    }
+
+    /// Creates an `Overlay` of directories in the file system.
+    ///
+    /// Directories are given from the lowest layer to the highest one, ie the
+    /// last directory has the highest priority.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if one of the paths is not a valid readable
+    /// directory.
+    pub fn from_dirs<I>(dirs: I) -> io::Result<Overlay>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        let mut overlay = Overlay::new();
+        for dir in dirs {
+            overlay.push(FileSystem::new(dir)?);
+        }
+        Ok(overlay)
+    }
+
+    /// Adds a layer on top of the existing ones.
+    #[inline]
+    pub fn push<S>(&mut self, layer: S)
+    where
+        S: Source + Send + Sync + 'static,
+    {
+        self.layers.push(Arc::new(layer));
+    }
+
+    /// Returns the number of layers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns `true` if the `Overlay` has no layer.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+impl Source for Overlay {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        let mut err = None;
+
+        for layer in self.layers.iter().rev() {
+            match layer.read(id, ext) {
+                Ok(content) => return Ok(content),
+                Err(e) => {
+                    if err.is_none() || e.kind() != io::ErrorKind::NotFound {
+                        err = Some(e);
+                    }
+                },
+            }
+        }
+
+        Err(err.unwrap_or_else(|| io::ErrorKind::NotFound.into()))
+    }
+
+    fn read_asset(&self, id: &str, ext: &str, exts: &[&str]) -> io::Result<FileContent<'_>> {
+        let layer = self.layers.iter().rev()
+            .find(|layer| exts.iter().any(|ext| layer.exists(DirEntry::File(id, ext))));
+
+        match layer {
+            Some(layer) => layer.read_asset(id, ext, exts),
+            None => self.read(id, ext),
+        }
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let mut found = false;
+        let mut files = HashSet::new();
+        let mut dirs = HashSet::new();
+
+        for layer in self.layers.iter().rev() {
+            let res = layer.read_dir(id, &mut |entry| match entry {
+                DirEntry::File(id, ext) => {
+                    if files.insert((id.to_owned(), ext.to_owned())) {
+                        f(entry);
+                    }
+                },
+                DirEntry::Directory(id) => {
+                    if dirs.insert(id.to_owned()) {
+                        f(entry);
+                    }
+                },
+            });
+            found |= res.is_ok();
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(io::ErrorKind::NotFound.into())
+        }
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        self.layers.iter().any(|layer| layer.exists(entry))
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    fn watch(&self) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        self.watch_with(&HotReloadConfig::default())
+    }
+
+    #[cfg(feature = "hot-reloading")]
+    fn watch_with(&self, config: &HotReloadConfig) -> Result<Option<Box<dyn Watcher>>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut watchers = Vec::new();
+
+        for layer in &self.layers {
+            if let Some(watcher) = layer.watch_with(config)? {
+                watchers.push(watcher);
+            }
+        }
+
+        if watchers.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Box::new(OverlayWatcher {
+            source: self.clone(),
+            watchers,
+        })))
+    }
+}
+
+impl fmt::Debug for Overlay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Overlay").field("layers", &self.layers.len()).finish()
+    }
+}
+
+
+/// Forwards the events of the watchers of each layer.
+#[cfg(feature = "hot-reloading")]
+struct OverlayWatcher {
+    source: Overlay,
+    watchers: Vec<Box<dyn Watcher>>,
+}
+
+#[cfg(feature = "hot-reloading")]
+impl Watcher for OverlayWatcher {
+    fn source(&self) -> &dyn Source {
+        &self.source
+    }
+
+    fn poll(&mut self, f: &mut dyn FnMut(Event)) {
+        for watcher in &mut self.watchers {
+            watcher.poll(f);
+        }
+    }
+
+    fn add_entry(&mut self, entry: DirEntry, recursive: bool) {
+        for watcher in &mut self.watchers {
+            watcher.add_entry(entry, recursive);
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    convert::TryInto,
+    fmt,
+    fs,
+    io::{self, Write},
+    path::Path,
+    str,
+};
+
+use super::{DirEntry, FileContent, Source};
+
+
+// A pack is made of the following parts, in this order. All integers are
+// stored in little endian.
+//
+// - A header (`HEADER_LEN` bytes):
+//   - the magic number `MAGIC`
+//   - the version of the format (u32)
+//   - the alignment of file contents (u32)
+//   - the number of files, directories and directory entries (3 x u32)
+//   - the length of the string table (u32)
+// - The index of files, sorted by id then extension. Each file is described by
+//   the offset and length of its id and extension in the string table (4 x
+//   u32), then the offset and length of its content in the pack (2 x u64).
+// - The index of directories, sorted by id. Each directory is described by
+//   the offset and length of its id in the string table, then the position
+//   and number of its entries in the entry table (4 x u32).
+// - The table of directory entries. Each entry is the index of a file, or the
+//   index of a directory with the highest bit set (u32).
+// - The string table, which is valid UTF-8.
+// - The contents of files, each one aligned as given in the header.
+
+const MAGIC: [u8; 8] = *b"AMPACK\0\0";
+const VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 32;
+const FILE_LEN: usize = 32;
+const DIR_LEN: usize = 16;
+const ENTRY_LEN: usize = 4;
+
+const DIR_FLAG: u32 = 1 << 31;
+
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid pack: {}", msg))
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(bytes[pos..pos+4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(bytes[pos..pos+8].try_into().unwrap())
+}
+
+/// Returns the parent directory of an id.
+fn parent_of(id: &str) -> &str {
+    match id.rfind('.') {
+        Some(pos) => &id[..pos],
+        None => "",
+    }
+}
+
+
+/// The position of the different parts of a pack.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    n_files: usize,
+    n_dirs: usize,
+    n_entries: usize,
+
+    dirs: usize,
+    entries: usize,
+    strings: usize,
+    strings_len: usize,
+}
+
+impl Layout {
+    fn parse(bytes: &[u8]) -> io::Result<Layout> {
+        if bytes.len() < HEADER_LEN || bytes[..8] != MAGIC {
+            return Err(invalid_data("bad header"));
+        }
+        if u32_at(bytes, 8) != VERSION {
+            return Err(invalid_data("unsupported version"));
+        }
+
+        let n_files = u32_at(bytes, 16) as u64;
+        let n_dirs = u32_at(bytes, 20) as u64;
+        let n_entries = u32_at(bytes, 24) as u64;
+        let strings_len = u32_at(bytes, 28) as u64;
+
+        let dirs = HEADER_LEN as u64 + n_files * FILE_LEN as u64;
+        let entries = dirs + n_dirs * DIR_LEN as u64;
+        let strings = entries + n_entries * ENTRY_LEN as u64;
+
+        // Once this is checked, all these values fit in an `usize`
+        if strings + strings_len > bytes.len() as u64 {
+            return Err(invalid_data("truncated index"));
+        }
+
+        Ok(Layout {
+            n_files: n_files as usize,
+            n_dirs: n_dirs as usize,
+            n_entries: n_entries as usize,
+
+            dirs: dirs as usize,
+            entries: entries as usize,
+            strings: strings as usize,
+            strings_len: strings_len as usize,
+        })
+    }
+}
+
+
+/// A file in the index of a pack.
+struct FileRecord<'a> {
+    id: &'a [u8],
+    ext: &'a [u8],
+    offset: u64,
+    len: u64,
+}
+
+/// A directory in the index of a pack.
+struct DirRecord<'a> {
+    id: &'a [u8],
+    entries: usize,
+    len: usize,
+}
+
+
+/// A [`Source`] to load assets from a pack.
+///
+/// A pack is a file format designed for this crate, which is made of a sorted
+/// index of files and directories followed by their contents. Looking for a
+/// file is a binary search in the index, and reading it does not copy its
+/// content.
+///
+/// Packs are created with a [`PackBuilder`], or with the `pack` binary of this
+/// crate, which packs a directory:
+///
+/// ```text
+/// pack [--align <N>] <DIRECTORY> <OUTPUT>
+/// ```
+///
+/// The bytes of a pack can be stored in any type that implements
+/// `AsRef<[u8]>`, which enables to include a pack in the binary with
+/// `include_bytes!`, or to read it from a memory map. File contents are only
+/// aligned as requested with [`PackBuilder::align`] if these bytes are aligned
+/// too.
+///
+/// # Example
+///
+/// ```
+/// use assets_manager::{AssetCache, source::{FileSystem, Pack, PackBuilder}};
+/// # use assets_manager::{Asset, loader::{LoadFrom, ParseLoader}};
+/// # struct X(i32);
+/// # impl From<i32> for X { fn from(n: i32) -> X { X(n) } }
+/// # impl Asset for X {
+/// #     const EXTENSION: &'static str = "x";
+/// #     type Loader = LoadFrom<i32, ParseLoader>;
+/// # }
+///
+/// let mut builder = PackBuilder::new();
+/// builder.add_source(&FileSystem::new("assets")?, "test")?;
+///
+/// let mut bytes = Vec::new();
+/// builder.write(&mut bytes)?;
+///
+/// let cache = AssetCache::with_source(Pack::from_bytes(bytes)?)?;
+/// let x = cache.load::<X>("test.cache")?;
+/// assert_eq!(x.read().0, 42);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`Source`]: trait.Source.html
+/// [`PackBuilder`]: struct.PackBuilder.html
+/// [`PackBuilder::align`]: struct.PackBuilder.html#method.align
+pub struct Pack<D = Vec<u8>> {
+    data: D,
+    layout: Layout,
+}
+
+impl Pack<Vec<u8>> {
+    /// Reads the pack at the given path.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file cannot be read or if it is not a
+    /// valid pack.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Pack<Vec<u8>>> {
+        Pack::from_bytes(fs::read(path)?)
+    }
+}
+
+impl<D: AsRef<[u8]>> Pack<D> {
+    /// Creates a `Pack` from its bytes.
+    ///
+    /// The whole index is checked, so later reads cannot fail because of an
+    /// invalid pack.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the bytes are not a valid pack.
+    pub fn from_bytes(data: D) -> io::Result<Pack<D>> {
+        let bytes = data.as_ref();
+        let layout = Layout::parse(bytes)?;
+        let pack = Pack { data, layout };
+        pack.validate()?;
+        Ok(pack)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    fn string(&self, pos: usize) -> &[u8] {
+        let bytes = self.bytes();
+        let start = self.layout.strings.checked_add(u32_at(bytes, pos) as usize);
+        let len = u32_at(bytes, pos + 4) as usize;
+        start.and_then(|start| bytes.get(start..start.checked_add(len)?)).unwrap_or_default()
+    }
+
+    fn file(&self, index: usize) -> FileRecord<'_> {
+        let pos = HEADER_LEN + index * FILE_LEN;
+        let bytes = self.bytes();
+
+        FileRecord {
+            id: self.string(pos),
+            ext: self.string(pos + 8),
+            offset: u64_at(bytes, pos + 16),
+            len: u64_at(bytes, pos + 24),
+        }
+    }
+
+    fn dir(&self, index: usize) -> DirRecord<'_> {
+        let pos = self.layout.dirs + index * DIR_LEN;
+        let bytes = self.bytes();
+
+        DirRecord {
+            id: self.string(pos),
+            entries: u32_at(bytes, pos + 8) as usize,
+            len: u32_at(bytes, pos + 12) as usize,
+        }
+    }
+
+    fn entry(&self, index: usize) -> u32 {
+        u32_at(self.bytes(), self.layout.entries + index * ENTRY_LEN)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let bytes = self.bytes();
+        let layout = self.layout;
+
+        let strings = &bytes[layout.strings..layout.strings + layout.strings_len];
+        let strings = str::from_utf8(strings).map_err(|_| invalid_data("invalid string"))?;
+        let check_str = |pos: usize| {
+            // These values come from the file, so they can overflow on 32-bit
+            // targets
+            let start = u32_at(bytes, pos) as usize;
+            let end = start.checked_add(u32_at(bytes, pos + 4) as usize);
+            match end.and_then(|end| strings.get(start..end)) {
+                Some(_) => Ok(()),
+                None => Err(invalid_data("invalid string")),
+            }
+        };
+
+        for i in 0..layout.n_files {
+            let pos = HEADER_LEN + i * FILE_LEN;
+            check_str(pos)?;
+            check_str(pos + 8)?;
+
+            let file = self.file(i);
+            match file.offset.checked_add(file.len) {
+                Some(end) if end <= bytes.len() as u64 => (),
+                _ => return Err(invalid_data("truncated file")),
+            }
+
+            if i > 0 && self.file(i - 1).key().cmp(&file.key()) != Ordering::Less {
+                return Err(invalid_data("unsorted files"));
+            }
+        }
+
+        for i in 0..layout.n_dirs {
+            check_str(layout.dirs + i * DIR_LEN)?;
+
+            let dir = self.dir(i);
+            match dir.entries.checked_add(dir.len) {
+                Some(end) if end <= layout.n_entries => (),
+                _ => return Err(invalid_data("invalid directory")),
+            }
+
+            if i > 0 && self.dir(i - 1).id >= dir.id {
+                return Err(invalid_data("unsorted directories"));
+            }
+        }
+
+        for i in 0..layout.n_entries {
+            let entry = self.entry(i);
+            let valid = if entry & DIR_FLAG != 0 {
+                ((entry & !DIR_FLAG) as usize) < layout.n_dirs
+            } else {
+                (entry as usize) < layout.n_files
+            };
+            if !valid {
+                return Err(invalid_data("invalid directory entry"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_file(&self, id: &str, ext: &str) -> Option<FileRecord<'_>> {
+        let key = (id.as_bytes(), ext.as_bytes());
+        let index = binary_search(self.layout.n_files, |i| self.file(i).key().cmp(&key))?;
+        Some(self.file(index))
+    }
+
+    fn find_dir(&self, id: &str) -> Option<DirRecord<'_>> {
+        let index = binary_search(self.layout.n_dirs, |i| self.dir(i).id.cmp(id.as_bytes()))?;
+        Some(self.dir(index))
+    }
+}
+
+impl FileRecord<'_> {
+    fn key(&self) -> (&[u8], &[u8]) {
+        (self.id, self.ext)
+    }
+}
+
+/// Finds the index for which `f` returns `Equal` in a sorted sequence.
+fn binary_search(len: usize, f: impl Fn(usize) -> Ordering) -> Option<usize> {
+    let (mut low, mut high) = (0, len);
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match f(mid) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Some(mid),
+        }
+    }
+
+    None
+}
+
+/// Strings were checked when the pack was created.
+fn as_str(bytes: &[u8]) -> &str {
+    str::from_utf8(bytes).unwrap_or_default()
+}
+
+impl<D: AsRef<[u8]>> Source for Pack<D> {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        let file = self.find_file(id, ext).ok_or(io::ErrorKind::NotFound)?;
+
+        let start = file.offset as usize;
+        let content = &self.bytes()[start..start + file.len as usize];
+        Ok(FileContent::Slice(content))
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let dir = self.find_dir(id).ok_or(io::ErrorKind::NotFound)?;
+
+        for i in dir.entries..dir.entries + dir.len {
+            let entry = self.entry(i);
+
+            if entry & DIR_FLAG != 0 {
+                let dir = self.dir((entry & !DIR_FLAG) as usize);
+                f(DirEntry::Directory(as_str(dir.id)));
+            } else {
+                let file = self.file(entry as usize);
+                f(DirEntry::File(as_str(file.id), as_str(file.ext)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        match entry {
+            DirEntry::File(id, ext) => self.find_file(id, ext).is_some(),
+            DirEntry::Directory(id) => self.find_dir(id).is_some(),
+        }
+    }
+}
+
+impl<D> fmt::Debug for Pack<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pack")
+            .field("files", &self.layout.n_files)
+            .field("dirs", &self.layout.n_dirs)
+            .finish()
+    }
+}
+
+
+/// Creates a [`Pack`].
+///
+/// Files are added to the builder with their id and extension, and the
+/// directories that contain them are created automatically.
+///
+/// [`Pack`]: struct.Pack.html
+#[derive(Debug, Clone)]
+pub struct PackBuilder {
+    align: usize,
+    files: BTreeMap<(String, String), Vec<u8>>,
+    dirs: BTreeSet<String>,
+}
+
+impl PackBuilder {
+    /// Creates an empty `PackBuilder`.
+    pub fn new() -> PackBuilder {
+        let mut dirs = BTreeSet::new();
+        dirs.insert(String::new());
+
+        PackBuilder {
+            align: 1,
+            files: BTreeMap::new(),
+            dirs,
+        }
+    }
+
+    /// Sets the alignment of the content of files in the pack.
+    ///
+    /// By default, contents are not aligned.
+    ///
+    /// Contents are aligned relative to the start of the pack, so they are
+    /// only aligned in memory if the bytes given to [`Pack::from_bytes`] are
+    /// themselves aligned to `align`. This is not the case for a `Vec<u8>`
+    /// (eg with [`Pack::open`]), and a memory map is only aligned if it starts
+    /// at an offset of the file that is a multiple of `align`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    ///
+    /// [`Pack::from_bytes`]: struct.Pack.html#method.from_bytes
+    /// [`Pack::open`]: struct.Pack.html#method.open
+    pub fn align(&mut self, align: usize) -> &mut PackBuilder {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        self.align = align;
+        self
+    }
+
+    /// Adds a file to the pack, replacing the previous one with the same id
+    /// and extension if any.
+    pub fn add_file(&mut self, id: &str, ext: &str, content: impl Into<Vec<u8>>) -> &mut PackBuilder {
+        self.add_dir(parent_of(id));
+        self.files.insert((id.to_owned(), ext.to_owned()), content.into());
+        self
+    }
+
+    /// Adds a directory to the pack, even if it does not contain any file.
+    pub fn add_dir(&mut self, id: &str) -> &mut PackBuilder {
+        if !self.dirs.contains(id) {
+            self.dirs.insert(id.to_owned());
+            if !id.is_empty() {
+                self.add_dir(parent_of(id));
+            }
+        }
+        self
+    }
+
+    /// Recursively adds the files of a directory of a source.
+    ///
+    /// Files keep the ids they have in the source.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a file or a directory cannot be read.
+    pub fn add_source<S: Source + ?Sized>(&mut self, source: &S, id: &str) -> io::Result<()> {
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+
+        source.read_dir(id, &mut |entry| match entry {
+            DirEntry::File(id, ext) => files.push((id.to_owned(), ext.to_owned())),
+            DirEntry::Directory(id) => dirs.push(id.to_owned()),
+        })?;
+
+        self.add_dir(id);
+
+        for (id, ext) in files {
+            let content = source.read(&id, &ext)?;
+            self.add_file(&id, &ext, content.into_owned());
+        }
+
+        for id in dirs {
+            self.add_source(source, &id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the pack.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if writing fails, or if the pack is too big for
+    /// the format.
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "pack too big");
+        let to_u32 = |n: usize| -> io::Result<u32> { n.try_into().map_err(|_| too_big()) };
+
+        let dirs: Vec<&str> = self.dirs.iter().map(|id| &**id).collect();
+        let dir_index = |id| dirs.binary_search(&id).unwrap() as u32;
+
+        if dirs.len() as u32 >= DIR_FLAG {
+            return Err(too_big());
+        }
+
+        // Compute directory entries
+        let mut children = vec![Vec::new(); dirs.len()];
+        for (i, (id, _)) in self.files.keys().enumerate() {
+            children[dir_index(parent_of(id)) as usize].push(to_u32(i)?);
+        }
+        for (i, id) in dirs.iter().enumerate().skip(1) {
+            children[dir_index(parent_of(id)) as usize].push(to_u32(i)? | DIR_FLAG);
+        }
+
+        // Compute the string table
+        let mut strings = String::new();
+        let mut push_str = |s: &str| -> io::Result<[u8; 8]> {
+            let mut pos = [0; 8];
+            pos[..4].copy_from_slice(&to_u32(strings.len())?.to_le_bytes());
+            pos[4..].copy_from_slice(&to_u32(s.len())?.to_le_bytes());
+            strings.push_str(s);
+            Ok(pos)
+        };
+
+        let mut file_index = Vec::with_capacity(self.files.len() * FILE_LEN);
+        let mut file_strings = Vec::with_capacity(self.files.len());
+        for (id, ext) in self.files.keys() {
+            file_strings.push((push_str(id)?, push_str(ext)?));
+        }
+        let mut dir_index_bytes = Vec::with_capacity(dirs.len() * DIR_LEN);
+        let mut n_entries = 0;
+        for (id, entries) in dirs.iter().zip(&children) {
+            dir_index_bytes.extend_from_slice(&push_str(id)?);
+            dir_index_bytes.extend_from_slice(&to_u32(n_entries)?.to_le_bytes());
+            dir_index_bytes.extend_from_slice(&to_u32(entries.len())?.to_le_bytes());
+            n_entries += entries.len();
+        }
+
+        // Compute the position of contents
+        let align = |pos: usize| (pos + self.align - 1) & !(self.align - 1);
+        let index_len = HEADER_LEN
+            + self.files.len() * FILE_LEN
+            + dirs.len() * DIR_LEN
+            + n_entries * ENTRY_LEN
+            + strings.len();
+
+        let mut offset = align(index_len);
+        for ((id_pos, ext_pos), content) in file_strings.iter().zip(self.files.values()) {
+            file_index.extend_from_slice(id_pos);
+            file_index.extend_from_slice(ext_pos);
+            file_index.extend_from_slice(&(offset as u64).to_le_bytes());
+            file_index.extend_from_slice(&(content.len() as u64).to_le_bytes());
+            offset = align(offset + content.len());
+        }
+
+        // Write everything
+        out.write_all(&MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&to_u32(self.align)?.to_le_bytes())?;
+        out.write_all(&to_u32(self.files.len())?.to_le_bytes())?;
+        out.write_all(&to_u32(dirs.len())?.to_le_bytes())?;
+        out.write_all(&to_u32(n_entries)?.to_le_bytes())?;
+        out.write_all(&to_u32(strings.len())?.to_le_bytes())?;
+
+        out.write_all(&file_index)?;
+        out.write_all(&dir_index_bytes)?;
+        for entry in children.iter().flatten() {
+            out.write_all(&entry.to_le_bytes())?;
+        }
+        out.write_all(strings.as_bytes())?;
+
+        let padding = vec![0; self.align];
+        let mut pos = index_len;
+        for content in self.files.values() {
+            out.write_all(&padding[..align(pos) - pos])?;
+            out.write_all(content)?;
+            pos = align(pos) + content.len();
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PackBuilder {
+    #[inline]
+    fn default() -> PackBuilder {
+        PackBuilder::new()
+    }
+}
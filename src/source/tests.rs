@@ -0,0 +1,650 @@
+use super::*;
+
+fn entries<S: Source>(source: &S, id: &str) -> Vec<(String, Option<String>)> {
+    let mut entries = Vec::new();
+    source.read_dir(id, &mut |entry| match entry {
+        DirEntry::File(id, ext) => entries.push((id.to_owned(), Some(ext.to_owned()))),
+        DirEntry::Directory(id) => entries.push((id.to_owned(), None)),
+    }).unwrap();
+    entries.sort();
+    entries
+}
+
+/// Checks that a source has the same tree as the expected one
+fn compare<A: Source, B: Source>(expected: &A, source: &B, id: &str) {
+    let entries = entries(expected, id);
+    assert_eq!(entries, self::entries(source, id));
+
+    for (id, ext) in entries {
+        match ext {
+            Some(ext) => {
+                assert!(source.exists(DirEntry::File(&id, &ext)));
+                assert_eq!(*expected.read(&id, &ext).unwrap(), *source.read(&id, &ext).unwrap());
+            },
+            None => {
+                assert!(source.exists(DirEntry::Directory(&id)));
+                compare(expected, source, &id);
+            },
+        }
+    }
+}
+
+mod filesystem {
+    use super::*;
+
+    #[test]
+    fn read_ok() {
+        let fs = FileSystem::new("assets").unwrap();
+
+        let content = fs.read("test.cache", "x").unwrap();
+        assert_eq!(&*content, b"42");
+    }
+
+    #[test]
+    fn read_err() {
+        let fs = FileSystem::new("assets").unwrap();
+
+        assert!(fs.read("test.cache", "y").is_err());
+        assert!(fs.read("test.missing", "x").is_err());
+    }
+
+    #[test]
+    fn read_dir() {
+        let fs = FileSystem::new("assets").unwrap();
+
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+
+        fs.read_dir("test", &mut |entry| match entry {
+            DirEntry::File(id, ext) => files.push(format!("{}.{}", id, ext)),
+            DirEntry::Directory(id) => dirs.push(id.to_owned()),
+        }).unwrap();
+
+        files.sort();
+        dirs.sort();
+        assert_eq!(files, ["test.a.x", "test.b.x", "test.cache.x"]);
+        assert_eq!(dirs, ["test.hot_asset", "test.hot_dir", "test.hot_overlay", "test.overlay"]);
+    }
+
+    #[test]
+    fn exists() {
+        let fs = FileSystem::new("assets").unwrap();
+
+        assert!(fs.exists(DirEntry::File("test.cache", "x")));
+        assert!(!fs.exists(DirEntry::File("test.cache", "y")));
+        assert!(fs.exists(DirEntry::Directory("test")));
+        assert!(fs.exists(DirEntry::Directory("")));
+        assert!(!fs.exists(DirEntry::Directory("test.cache")));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap() {
+        let fs = unsafe { FileSystem::new("assets").unwrap().with_mmap() };
+
+        let content = fs.read("test.cache", "x").unwrap();
+        assert!(matches!(content, FileContent::Owned(_)));
+        assert_eq!(&*content, b"42");
+        assert!(fs.read("test.missing", "x").is_err());
+        drop(content);
+
+        let cache = crate::AssetCache::with_source(fs).unwrap();
+        assert_eq!(*cache.load::<crate::tests::X>("test.cache").unwrap().read(), crate::tests::X(42));
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compressed {
+    use super::*;
+    use crate::{AssetCache, tests::{X, TempDir, COMPRESSIONS, compress}};
+
+    fn source(ext: &str) -> (TempDir, FileSystem) {
+        let dir = TempDir::new();
+        dir.write(&format!("a.x.{}", ext), &compress(b"1", ext));
+        dir.write("b.x", b"2");
+        dir.write(&format!("b.x.{}", ext), &compress(b"3", ext));
+        dir.write(&format!("c.{}", ext), b"4");
+
+        let fs = FileSystem::new(&dir.0).unwrap();
+        (dir, fs)
+    }
+
+    #[test]
+    fn read() {
+        for ext in COMPRESSIONS {
+            let (_dir, fs) = source(ext);
+
+            assert_eq!(&*fs.read("a", "x").unwrap(), b"1");
+            assert_eq!(&*fs.read("b", "x").unwrap(), b"2");
+            assert_eq!(&*fs.read("c", ext).unwrap(), b"4");
+            assert!(fs.read("c", "").is_err());
+            assert!(fs.read("a", "y").is_err());
+        }
+    }
+
+    #[test]
+    fn read_dir() {
+        for ext in COMPRESSIONS {
+            let (_dir, fs) = source(ext);
+
+            let mut files = Vec::new();
+            fs.read_dir("", &mut |entry| {
+                if let DirEntry::File(id, ext) = entry {
+                    files.push(format!("{}.{}", id, ext));
+                }
+            }).unwrap();
+
+            files.sort();
+            assert_eq!(files, ["a.x", "b.x", format!("c.{}", ext).as_str()]);
+        }
+    }
+
+    #[test]
+    fn exists() {
+        for ext in COMPRESSIONS {
+            let (_dir, fs) = source(ext);
+
+            assert!(fs.exists(DirEntry::File("a", "x")));
+            assert!(fs.exists(DirEntry::File("c", ext)));
+            assert!(!fs.exists(DirEntry::File("c", "")));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for ext in COMPRESSIONS {
+            let dir = TempDir::new();
+            dir.write(&format!("a.x.{}", ext), b"not compressed");
+
+            let fs = FileSystem::new(&dir.0).unwrap();
+            assert!(fs.read("a", "x").is_err());
+        }
+    }
+
+    #[test]
+    fn load_from_cache() {
+        for ext in COMPRESSIONS {
+            let (_dir, fs) = source(ext);
+            let cache = AssetCache::with_source(fs).unwrap();
+
+            assert_eq!(*cache.load::<X>("a").unwrap().read(), X(1));
+
+            let mut loaded: Vec<_> = cache.load_dir::<X>("").unwrap()
+                .iter().map(|x| x.read().0).collect();
+            loaded.sort();
+            assert_eq!(loaded, [1, 2]);
+        }
+    }
+}
+
+mod custom {
+    use super::*;
+    use crate::{AssetCache, tests::X};
+
+    struct Single;
+
+    impl Source for Single {
+        fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+            if self.exists(DirEntry::File(id, ext)) {
+                Ok(FileContent::Slice(b"12"))
+            } else {
+                Err(io::ErrorKind::NotFound.into())
+            }
+        }
+
+        fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+            if id.is_empty() {
+                f(DirEntry::File("single", "x"));
+                Ok(())
+            } else {
+                Err(io::ErrorKind::NotFound.into())
+            }
+        }
+
+        fn exists(&self, entry: DirEntry) -> bool {
+            match entry {
+                DirEntry::File(id, ext) => id == "single" && ext == "x",
+                DirEntry::Directory(id) => id.is_empty(),
+            }
+        }
+    }
+
+    #[test]
+    fn load() {
+        let cache = AssetCache::with_source(Single).unwrap();
+
+        assert_eq!(*cache.load::<X>("single").unwrap().read(), X(12));
+        assert!(cache.load::<X>("other").is_err());
+    }
+
+    #[test]
+    fn load_dir() {
+        let cache = AssetCache::with_source(Single).unwrap();
+
+        let loaded: Vec<_> = cache.load_dir::<X>("").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        assert_eq!(loaded, [12]);
+        assert!(cache.load_dir::<X>("dir").is_err());
+    }
+}
+
+mod overlay {
+    use super::*;
+    use crate::{AssetCache, tests::X};
+
+    fn source() -> Overlay {
+        Overlay::from_dirs(&["assets/test/overlay/base", "assets/test/overlay/top"]).unwrap()
+    }
+
+    #[test]
+    fn read() {
+        let overlay = source();
+
+        assert_eq!(&*overlay.read("a", "x").unwrap(), b"10");
+        assert_eq!(&*overlay.read("b", "x").unwrap(), b"2");
+        assert_eq!(&*overlay.read("c", "x").unwrap(), b"30");
+        assert!(overlay.read("d", "x").is_err());
+    }
+
+    #[test]
+    fn read_dir() {
+        let overlay = source();
+
+        let mut entries = Vec::new();
+        overlay.read_dir("", &mut |entry| match entry {
+            DirEntry::File(id, ext) => entries.push(format!("{}.{}", id, ext)),
+            DirEntry::Directory(id) => entries.push(format!("{}/", id)),
+        }).unwrap();
+
+        entries.sort();
+        assert_eq!(entries, ["a.x", "b.x", "c.x", "sub/"]);
+
+        assert!(overlay.read_dir("sub", &mut |_| ()).is_ok());
+        assert!(overlay.read_dir("missing", &mut |_| ()).is_err());
+    }
+
+    #[test]
+    fn exists() {
+        let overlay = source();
+
+        assert!(overlay.exists(DirEntry::File("a", "x")));
+        assert!(overlay.exists(DirEntry::File("b", "x")));
+        assert!(overlay.exists(DirEntry::File("sub.d", "x")));
+        assert!(!overlay.exists(DirEntry::File("sub", "x")));
+        assert!(overlay.exists(DirEntry::Directory("sub")));
+    }
+
+    #[test]
+    fn empty() {
+        let overlay = Overlay::new();
+
+        assert!(overlay.is_empty());
+        assert!(overlay.read("a", "x").is_err());
+        assert!(overlay.read_dir("", &mut |_| ()).is_err());
+        assert!(Overlay::from_dirs(&["assets", "missing"]).is_err());
+    }
+
+    #[test]
+    fn load_from_cache() {
+        let cache = AssetCache::with_source(source()).unwrap();
+
+        assert_eq!(*cache.load::<X>("a").unwrap().read(), X(10));
+
+        let mut loaded: Vec<_> = cache.load_dir::<X>("").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        loaded.sort();
+        assert_eq!(loaded, [2, 10, 30]);
+    }
+
+    #[test]
+    fn higher_layer_with_other_extension() {
+        use crate::{Asset, loader, source::Memory};
+
+        struct Y(i32);
+
+        impl From<i32> for Y {
+            fn from(n: i32) -> Y {
+                Y(n)
+            }
+        }
+
+        impl Asset for Y {
+            type Loader = loader::LoadFrom<i32, loader::ParseLoader>;
+            const EXTENSIONS: &'static [&'static str] = &["x", "y"];
+        }
+
+        let base = Memory::new();
+        base.insert("a", "x", "1");
+        base.insert("b", "x", "2");
+        let top = Memory::new();
+        top.insert("a", "y", "10");
+        top.insert("b", "x", "20");
+        top.insert("b", "y", "30");
+
+        let mut overlay = Overlay::new();
+        overlay.push(base);
+        overlay.push(top);
+        let cache = AssetCache::with_source(overlay).unwrap();
+
+        assert_eq!(cache.load::<Y>("a").unwrap().read().0, 10);
+        assert_eq!(cache.load::<Y>("b").unwrap().read().0, 20);
+
+        // Assets with several extensions are listed once
+        let ids: Vec<_> = cache.load_dir::<Y>("").unwrap().iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["a", "b"]);
+    }
+}
+
+mod pack {
+    use super::*;
+    use crate::{AssetCache, tests::X};
+
+    fn source(align: usize) -> Pack {
+        let fs = FileSystem::new("assets").unwrap();
+
+        let mut builder = PackBuilder::new();
+        builder.align(align).add_source(&fs, "").unwrap();
+        builder.add_dir("empty.dir");
+
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        Pack::from_bytes(bytes).unwrap()
+    }
+
+    #[test]
+    fn same_as_filesystem() {
+        let fs = FileSystem::new("assets").unwrap();
+
+        for &align in &[1, 8, 64] {
+            let pack = source(align);
+            compare(&fs, &pack, "test");
+
+            pack.read_dir("", &mut |entry| {
+                if let DirEntry::File(id, ext) = entry {
+                    assert_eq!(*fs.read(id, ext).unwrap(), *pack.read(id, ext).unwrap());
+                }
+            }).unwrap();
+        }
+    }
+
+    #[test]
+    fn aligned() {
+        let mut bytes = Vec::new();
+        PackBuilder::new()
+            .align(64)
+            .add_file("a", "x", "1")
+            .add_file("b", "x", "22")
+            .write(&mut bytes).unwrap();
+
+        let start = bytes.as_ptr() as usize;
+        let pack = Pack::from_bytes(bytes).unwrap();
+
+        for &id in &["a", "b"] {
+            let content = pack.read(id, "x").unwrap();
+            assert!(matches!(content, FileContent::Slice(_)));
+            assert_eq!((content.as_ptr() as usize - start) % 64, 0);
+        }
+    }
+
+    #[test]
+    fn empty_dir() {
+        let pack = source(1);
+
+        assert!(pack.exists(DirEntry::Directory("empty")));
+        assert!(pack.exists(DirEntry::Directory("empty.dir")));
+        assert_eq!(entries(&pack, "empty"), [("empty.dir".to_owned(), None)]);
+        assert!(entries(&pack, "empty.dir").is_empty());
+    }
+
+    #[test]
+    fn missing() {
+        let pack = source(1);
+
+        assert!(pack.read("test.cache", "y").is_err());
+        assert!(pack.read("test", "x").is_err());
+        assert!(!pack.exists(DirEntry::Directory("test.cache")));
+        assert!(pack.read_dir("test.missing", &mut |_| ()).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = Vec::new();
+        PackBuilder::new().add_file("a", "x", "1").write(&mut bytes).unwrap();
+        assert!(Pack::from_bytes(&bytes[..]).is_ok());
+
+        assert!(Pack::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Pack::from_bytes(&b"not a pack"[..]).is_err());
+
+        let mut bad_version = bytes.clone();
+        bad_version[8] = 42;
+        assert!(Pack::from_bytes(bad_version).is_err());
+
+        // Offsets and lengths that overflow when added
+        let is_invalid_data = |bytes: Vec<u8>| {
+            Pack::from_bytes(bytes).err().map(|err| err.kind()) == Some(io::ErrorKind::InvalidData)
+        };
+
+        let mut bad_string = bytes.clone();
+        bad_string[32..40].copy_from_slice(&[0xff; 8]);
+        assert!(is_invalid_data(bad_string));
+
+        let mut bad_dir = bytes;
+        bad_dir[72..80].copy_from_slice(&[0xff; 8]);
+        assert!(is_invalid_data(bad_dir));
+    }
+
+    #[test]
+    fn load_from_cache() {
+        let cache = AssetCache::with_source(source(8)).unwrap();
+
+        assert_eq!(*cache.load::<X>("test.cache").unwrap().read(), X(42));
+
+        let mut loaded: Vec<_> = cache.load_dir::<X>("test").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        loaded.sort();
+        assert_eq!(loaded, [-7, 42]);
+    }
+}
+
+#[cfg(feature = "zip")]
+mod zip {
+    use super::*;
+    use crate::{AssetCache, tests::X};
+    use ::zip::{ZipWriter, write::FileOptions};
+    use std::io::{Cursor, Write};
+
+    fn archive() -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+
+        zip.add_directory("test/empty/", options).unwrap();
+
+        let files: &[(&str, &[u8])] = &[
+            ("test/a.x", b"Error"),
+            ("test/b.x", b"-7"),
+            ("test/cache.x", b"42"),
+            ("test/sub/c.x", b"8"),
+            ("root.x", b"1"),
+        ];
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn source() -> Zip<Cursor<Vec<u8>>> {
+        Zip::from_reader(Cursor::new(archive())).unwrap()
+    }
+
+    #[test]
+    fn read() {
+        let zip = source();
+
+        assert_eq!(&*zip.read("test.cache", "x").unwrap(), b"42");
+        assert_eq!(&*zip.read("root", "x").unwrap(), b"1");
+        assert!(zip.read("test.cache", "y").is_err());
+        assert!(zip.read("test.missing", "x").is_err());
+    }
+
+    #[test]
+    fn read_dir() {
+        let zip = source();
+
+        let mut entries = Vec::new();
+        zip.read_dir("test", &mut |entry| match entry {
+            DirEntry::File(id, ext) => entries.push(format!("{}.{}", id, ext)),
+            DirEntry::Directory(id) => entries.push(format!("{}/", id)),
+        }).unwrap();
+
+        entries.sort();
+        assert_eq!(entries, ["test.a.x", "test.b.x", "test.cache.x", "test.empty/", "test.sub/"]);
+
+        assert!(zip.read_dir("test.missing", &mut |_| ()).is_err());
+    }
+
+    #[test]
+    fn exists() {
+        let zip = source();
+
+        assert!(zip.exists(DirEntry::File("test.sub.c", "x")));
+        assert!(!zip.exists(DirEntry::File("test.sub", "x")));
+        assert!(zip.exists(DirEntry::Directory("")));
+        assert!(zip.exists(DirEntry::Directory("test.sub")));
+        assert!(zip.exists(DirEntry::Directory("test.empty")));
+        assert!(!zip.exists(DirEntry::Directory("test.cache")));
+    }
+
+    #[test]
+    fn load_from_cache() {
+        let cache = AssetCache::with_source(source()).unwrap();
+
+        assert_eq!(*cache.load::<X>("test.sub.c").unwrap().read(), X(8));
+
+        let mut loaded: Vec<_> = cache.load_dir::<X>("test").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        loaded.sort();
+        assert_eq!(loaded, [-7, 42]);
+    }
+
+    #[test]
+    fn open_file() {
+        let path = std::env::temp_dir().join(format!("assets_manager_{}.zip", rand::random::<u32>()));
+        std::fs::write(&path, archive()).unwrap();
+
+        let cache = AssetCache::with_source(Zip::open(&path).unwrap()).unwrap();
+        assert_eq!(*cache.load::<X>("root").unwrap().read(), X(1));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(feature = "embedded")]
+mod embedded {
+    use super::*;
+    use crate::{AssetCache, tests::X};
+
+    #[test]
+    fn same_as_filesystem() {
+        let fs = FileSystem::new("assets").unwrap();
+        let embedded = embed!("assets");
+
+        compare(&fs, &embedded, "");
+    }
+
+    #[test]
+    fn missing() {
+        let embedded = embed!("assets");
+
+        assert!(embedded.read("test.cache", "y").is_err());
+        assert!(!embedded.exists(DirEntry::Directory("test.cache")));
+        assert!(embedded.read_dir("test.missing", &mut |_| ()).is_err());
+    }
+
+    #[test]
+    fn load_from_cache() {
+        let cache = AssetCache::with_source(embed!("assets")).unwrap();
+
+        assert_eq!(*cache.load::<X>("test.cache").unwrap().read(), X(42));
+
+        let mut loaded: Vec<_> = cache.load_dir::<X>("test").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        loaded.sort();
+        assert_eq!(loaded, [-7, 42]);
+    }
+}
+
+mod memory {
+    use super::*;
+    use crate::{AssetCache, tests::X};
+
+    fn source() -> Memory {
+        let memory = Memory::new();
+        memory.insert("a", "x", "1");
+        memory.insert("a", "y", "2");
+        memory.insert("dir.b", "x", "3");
+        memory.insert("dir.sub.c", "x", "4");
+        memory
+    }
+
+    #[test]
+    fn read() {
+        let memory = source();
+
+        assert_eq!(&*memory.read("a", "x").unwrap(), b"1");
+        assert_eq!(&*memory.read("a", "y").unwrap(), b"2");
+        assert!(memory.read("a", "z").is_err());
+        assert!(memory.read("dir", "x").is_err());
+    }
+
+    #[test]
+    fn insert_remove() {
+        let memory = source();
+
+        memory.insert("a", "x", "10");
+        assert_eq!(&*memory.read("a", "x").unwrap(), b"10");
+
+        assert_eq!(memory.remove("dir.sub.c", "x").unwrap(), b"4");
+        assert!(memory.remove("dir.sub.c", "x").is_none());
+        assert!(!memory.exists(DirEntry::Directory("dir.sub")));
+        assert!(memory.exists(DirEntry::Directory("dir")));
+    }
+
+    #[test]
+    fn read_dir() {
+        let memory = source();
+
+        let mut entries = Vec::new();
+        memory.read_dir("", &mut |entry| match entry {
+            DirEntry::File(id, ext) => entries.push(format!("{}.{}", id, ext)),
+            DirEntry::Directory(id) => entries.push(format!("{}/", id)),
+        }).unwrap();
+
+        entries.sort();
+        assert_eq!(entries, ["a.x", "a.y", "dir/"]);
+
+        let mut entries = Vec::new();
+        memory.read_dir("dir", &mut |entry| match entry {
+            DirEntry::File(id, ext) => entries.push(format!("{}.{}", id, ext)),
+            DirEntry::Directory(id) => entries.push(format!("{}/", id)),
+        }).unwrap();
+
+        entries.sort();
+        assert_eq!(entries, ["dir.b.x", "dir.sub/"]);
+
+        assert!(memory.read_dir("missing", &mut |_| ()).is_err());
+        assert!(memory.read_dir("di", &mut |_| ()).is_err());
+    }
+
+    #[test]
+    fn load_from_cache() {
+        let cache = AssetCache::with_source(source()).unwrap();
+
+        assert_eq!(*cache.load::<X>("dir.sub.c").unwrap().read(), X(4));
+
+        let loaded: Vec<_> = cache.load_dir::<X>("dir").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        assert_eq!(loaded, [3]);
+    }
+}
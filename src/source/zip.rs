@@ -0,0 +1,190 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek},
+    path::Path,
+};
+
+use ::zip::ZipArchive;
+
+use crate::{
+    dirs::{id_of_relative_path, id_push},
+    utils::{HashMap, Mutex},
+};
+use super::{DirEntry, FileContent, Source};
+
+/// The maximum number of bytes allocated before reading a file.
+const MAX_PREALLOCATION: u64 = 1 << 20;
+
+
+/// An entry of a directory in a `Zip` archive.
+#[derive(Debug)]
+enum OwnedEntry {
+    File(Box<str>, Box<str>),
+    Dir(Box<str>),
+}
+
+impl OwnedEntry {
+    fn as_dir_entry(&self) -> DirEntry<'_> {
+        match self {
+            OwnedEntry::File(id, ext) => DirEntry::File(id, ext),
+            OwnedEntry::Dir(id) => DirEntry::Directory(id),
+        }
+    }
+}
+
+/// Register a directory and all its ancestors.
+fn register_dir(dirs: &mut HashMap<Box<str>, Vec<OwnedEntry>>, id: &str) {
+    if dirs.contains_key(id) {
+        return;
+    }
+    dirs.insert(id.into(), Vec::new());
+
+    if let Some(pos) = id.rfind('.') {
+        let parent = &id[..pos];
+        register_dir(dirs, parent);
+        dirs.get_mut(parent).unwrap().push(OwnedEntry::Dir(id.into()));
+    } else if !id.is_empty() {
+        dirs.get_mut("").unwrap().push(OwnedEntry::Dir(id.into()));
+    }
+}
+
+/// Returns the id of a directory given its path in the archive.
+fn dir_id_of(path: &Path) -> Option<String> {
+    let mut id = String::new();
+    for component in path.iter() {
+        id_push(&mut id, component.to_str()?);
+    }
+    Some(id)
+}
+
+/// Returns the parent directory of an id.
+fn parent_of(id: &str) -> &str {
+    match id.rfind('.') {
+        Some(pos) => &id[..pos],
+        None => "",
+    }
+}
+
+
+/// A [`Source`] to load assets from a zip archive.
+///
+/// Ids are constructed from the path of files within the archive, the same
+/// way as with a [`FileSystem`].
+///
+/// The archive is indexed when the `Zip` is created, so looking up a file is
+/// a cheap operation, but reading it requires to lock the archive.
+///
+/// This type is only available with the `zip` feature.
+///
+/// [`Source`]: trait.Source.html
+/// [`FileSystem`]: struct.FileSystem.html
+#[cfg_attr(docsrs, doc(cfg(feature = "zip")))]
+pub struct Zip<R = File> {
+    files: HashMap<Box<str>, Vec<(Box<str>, usize)>>,
+    dirs: HashMap<Box<str>, Vec<OwnedEntry>>,
+    archive: Mutex<ZipArchive<R>>,
+}
+
+impl Zip<File> {
+    /// Creates a `Zip` archive backed by the file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file cannot be opened or if it is not a
+    /// valid zip archive.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Zip<File>> {
+        let file = File::open(path)?;
+        Zip::from_reader(file)
+    }
+}
+
+impl<R: Read + Seek> Zip<R> {
+    /// Creates a `Zip` archive backed by a reader that supports seeking.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the reader does not contain a valid zip
+    /// archive.
+    pub fn from_reader(reader: R) -> io::Result<Zip<R>> {
+        let mut archive = ZipArchive::new(reader)?;
+
+        let mut files: HashMap<Box<str>, Vec<(Box<str>, usize)>> = HashMap::new();
+        let mut dirs = HashMap::new();
+        register_dir(&mut dirs, "");
+
+        for index in 0..archive.len() {
+            let file = archive.by_index(index)?;
+
+            let path = match file.enclosed_name() {
+                Some(path) => path.to_owned(),
+                None => continue,
+            };
+
+            if file.is_dir() {
+                if let Some(id) = dir_id_of(&path) {
+                    register_dir(&mut dirs, &id);
+                }
+                continue;
+            }
+
+            let (id, ext) = match id_of_relative_path(&path) {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let parent = parent_of(&id);
+            register_dir(&mut dirs, parent);
+            dirs.get_mut(parent).unwrap().push(OwnedEntry::File(id.as_str().into(), ext.into()));
+
+            files.entry(id.into()).or_default().push((ext.into(), index));
+        }
+
+        Ok(Zip {
+            files,
+            dirs,
+            archive: Mutex::new(archive),
+        })
+    }
+
+    fn index_of(&self, id: &str, ext: &str) -> Option<usize> {
+        let exts = self.files.get(id)?;
+        exts.iter().find(|(e, _)| &**e == ext).map(|&(_, index)| index)
+    }
+}
+
+impl<R: Read + Seek> Source for Zip<R> {
+    fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+        let index = self.index_of(id, ext).ok_or(io::ErrorKind::NotFound)?;
+
+        let mut archive = self.archive.lock();
+        let mut file = archive.by_index(index)?;
+
+        // The size comes from the archive, which may be corrupted, so it is
+        // only trusted up to a limit
+        let capacity = file.size().min(MAX_PREALLOCATION) as usize;
+        let mut content = Vec::with_capacity(capacity);
+        file.read_to_end(&mut content)?;
+
+        Ok(content.into())
+    }
+
+    fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+        let dir = self.dirs.get(id).ok_or(io::ErrorKind::NotFound)?;
+        dir.iter().map(OwnedEntry::as_dir_entry).for_each(f);
+        Ok(())
+    }
+
+    fn exists(&self, entry: DirEntry) -> bool {
+        match entry {
+            DirEntry::File(id, ext) => self.index_of(id, ext).is_some(),
+            DirEntry::Directory(id) => self.dirs.contains_key(id),
+        }
+    }
+}
+
+impl<R> fmt::Debug for Zip<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Zip").field("dirs", &self.dirs).finish()
+    }
+}
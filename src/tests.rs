@@ -14,6 +14,113 @@ impl Asset for X {
     const EXTENSION: &'static str = "x";
 }
 
+/// A temporary directory, removed when dropped.
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "hot-reloading"))]
+pub struct TempDir(pub std::path::PathBuf);
+
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "hot-reloading"))]
+impl TempDir {
+    pub fn new() -> TempDir {
+        let path = std::env::temp_dir().join(format!("assets_manager_{}", rand::random::<u32>()));
+        std::fs::create_dir(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn write(&self, name: &str, content: &[u8]) {
+        std::fs::write(self.0.join(name), content).unwrap();
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "hot-reloading"))]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Compression extensions supported with the enabled features.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub const COMPRESSIONS: &[&str] = &[
+    #[cfg(feature = "gzip")]
+    "gz",
+    #[cfg(feature = "zstd")]
+    "zst",
+];
+
+/// Compresses bytes in the format given by its extension.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub fn compress(content: &[u8], ext: &str) -> Vec<u8> {
+    match ext {
+        #[cfg(feature = "gzip")]
+        "gz" => {
+            use std::io::Write;
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap()
+        },
+        #[cfg(feature = "zstd")]
+        "zst" => zstd::stream::encode_all(content, 0).unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+/// Runs a future to completion on the current thread.
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll};
+
+    let mut future = Box::pin(future);
+    let waker = executor::waker();
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+/// Polls a future once on the current thread.
+pub fn poll_once<F: std::future::Future + Unpin>(future: &mut F) -> std::task::Poll<F::Output> {
+    let waker = executor::waker();
+    let mut cx = std::task::Context::from_waker(&waker);
+    std::pin::Pin::new(future).poll(&mut cx)
+}
+
+mod executor {
+    use std::{
+        task::{RawWaker, RawWakerVTable, Waker},
+        thread::{self, Thread},
+    };
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+    fn raw_waker(thread: Thread) -> RawWaker {
+        RawWaker::new(Box::into_raw(Box::new(thread)) as *const (), &VTABLE)
+    }
+
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        raw_waker((*(data as *const Thread)).clone())
+    }
+
+    unsafe fn wake(data: *const ()) {
+        Box::from_raw(data as *mut Thread).unpark();
+    }
+
+    unsafe fn wake_by_ref(data: *const ()) {
+        (*(data as *const Thread)).unpark();
+    }
+
+    unsafe fn drop(data: *const ()) {
+        std::mem::drop(Box::from_raw(data as *mut Thread));
+    }
+
+    /// A waker that unparks the current thread.
+    pub fn waker() -> Waker {
+        unsafe { Waker::from_raw(raw_waker(thread::current())) }
+    }
+}
+
 
 mod asset_cache {
     use crate::AssetCache;
@@ -81,6 +188,56 @@ mod asset_cache {
         assert_eq!(loaded, [-7, 42]);
     }
 
+    #[test]
+    fn load_dir_recursive() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let dir = cache.load_dir_recursive::<X>("test.overlay").unwrap();
+        let ids: Vec<_> = dir.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, [
+            "test.overlay.base.a",
+            "test.overlay.base.b",
+            "test.overlay.top.a",
+            "test.overlay.top.c",
+            "test.overlay.top.sub.d",
+        ]);
+        let loaded: Vec<_> = dir.iter().map(|x| x.read().0).collect();
+        assert_eq!(loaded, [1, 2, 10, 30, 4]);
+
+        // Non-recursive directories are cached separately
+        assert_eq!(cache.load_dir::<X>("test.overlay").unwrap().iter_all().count(), 0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn load_dir_par() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let loaded: Vec<_> = cache.load_dir_par::<X>("test").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        assert_eq!(loaded, [-7, 42]);
+
+        let dir = cache.load_dir::<X>("test").unwrap();
+        let ids: Vec<_> = dir.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["test.a", "test.b", "test.cache"]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn load_dir_par_many() {
+        use crate::source::Memory;
+
+        let source = Memory::new();
+        for i in 0..500 {
+            source.insert(&format!("dir.{:03}", i), "x", i.to_string());
+        }
+        let cache = AssetCache::with_source(source).unwrap();
+
+        let loaded: Vec<_> = cache.load_dir_par::<X>("dir").unwrap()
+            .iter().map(|x| x.read().0).collect();
+        assert_eq!(loaded, (0..500).collect::<Vec<_>>());
+    }
+
     #[test]
     fn load_dir_all() {
         let cache = AssetCache::new("assets").unwrap();
@@ -181,7 +338,7 @@ mod cache_entry {
         let x = rand::random::<i32>();
 
         let entry = CacheEntry::new(x);
-        let y = unsafe { entry.into_inner::<i32>() };
+        let y = unsafe { entry.into_inner::<i32>().unwrap() };
 
         assert_eq!(x, y);
     }
@@ -197,4 +354,794 @@ mod cache_entry {
             assert!(ref_1.ptr_eq(&ref_2));
         }
     }
+
+    #[test]
+    fn handle() {
+        let x = rand::random::<i32>();
+        let y = rand::random::<i32>();
+
+        let entry = CacheEntry::new(x);
+        let mut handle = unsafe { entry.get_handle::<i32>() };
+        assert_eq!(*handle.read(), x);
+        assert!(!handle.reloaded());
+
+        unsafe { entry.write(y); }
+        assert_eq!(*handle.read(), y);
+        assert!(handle.reloaded());
+
+        let entry = unsafe { entry.into_inner::<i32>().unwrap_err() };
+        drop(handle);
+        assert_eq!(unsafe { entry.into_inner::<i32>().unwrap() }, y);
+    }
+
+    #[cfg(feature = "arc-swap")]
+    #[test]
+    fn write_while_reading() {
+        let x = rand::random::<i32>();
+        let y = rand::random::<i32>();
+
+        let entry = CacheEntry::new(x);
+        unsafe {
+            let asset = entry.get_ref::<i32>();
+            let guard = asset.read();
+
+            // This would deadlock with a `RwLock`
+            entry.write(y);
+            assert_eq!(*guard, x);
+            assert_eq!(*asset.read(), y);
+        }
+    }
+}
+
+mod handle {
+    use crate::AssetCache;
+    use super::X;
+
+    #[test]
+    fn load() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let handle = cache.load_handle::<X>("test.cache").unwrap();
+        assert_eq!(*handle.read(), X(42));
+        assert!(handle.ptr_eq(&cache.load_handle("test.cache").unwrap()));
+        assert!(handle.as_asset_ref().ptr_eq(&cache.load("test.cache").unwrap()));
+
+        assert!(cache.load_handle::<X>("test.missing").is_err());
+    }
+
+    #[test]
+    fn reloaded() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let mut handle = cache.load_handle::<X>("test.cache").unwrap();
+        assert!(!handle.reloaded());
+        cache.force_reload::<X>("test.cache").unwrap();
+        assert!(handle.reloaded());
+        assert!(!handle.reloaded());
+    }
+
+    #[test]
+    fn outlive_cache() {
+        let mut cache = AssetCache::new("assets").unwrap();
+
+        let handle_1 = cache.load_handle::<X>("test.b").unwrap();
+        let handle_2 = cache.load_handle::<X>("test.cache").unwrap();
+
+        cache.remove::<X>("test.b");
+        assert!(cache.load_cached::<X>("test.b").is_none());
+        cache.clear();
+        drop(cache);
+
+        let thread = std::thread::spawn(move || *handle_1.read());
+        assert_eq!(thread.join().unwrap(), X(-7));
+        assert_eq!(*handle_2.read(), X(42));
+    }
+
+    #[test]
+    fn take() {
+        let mut cache = AssetCache::new("assets").unwrap();
+
+        let handle = cache.load_handle::<X>("test.cache").unwrap();
+        assert_eq!(cache.take::<X>("test.cache"), None);
+        assert!(cache.load_cached::<X>("test.cache").is_some());
+
+        drop(handle);
+        assert_eq!(cache.take("test.cache"), Some(X(42)));
+    }
+}
+
+mod asynchronous {
+    use crate::{
+        AssetCache,
+        source::{DirEntry, FileContent, Memory, Source},
+    };
+    use super::{X, block_on, poll_once};
+    use std::{
+        io,
+        sync::{Arc, Condvar, Mutex, atomic::{AtomicUsize, Ordering}},
+    };
+
+    /// A source that counts reads and blocks them until it is opened.
+    #[derive(Clone)]
+    struct Gated {
+        inner: Memory,
+        reads: Arc<AtomicUsize>,
+        gate: Arc<(Mutex<bool>, Condvar)>,
+    }
+
+    impl Gated {
+        fn new(inner: Memory) -> Gated {
+            Gated {
+                inner,
+                reads: Arc::new(AtomicUsize::new(0)),
+                gate: Arc::new((Mutex::new(false), Condvar::new())),
+            }
+        }
+
+        fn open(&self) {
+            *self.gate.0.lock().unwrap() = true;
+            self.gate.1.notify_all();
+        }
+    }
+
+    impl Source for Gated {
+        fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+            let mut open = self.gate.0.lock().unwrap();
+            while !*open {
+                open = self.gate.1.wait(open).unwrap();
+            }
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.inner.read(id, ext)
+        }
+
+        fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+            self.inner.read_dir(id, f)
+        }
+
+        fn exists(&self, entry: DirEntry) -> bool {
+            self.inner.exists(entry)
+        }
+    }
+
+    #[test]
+    fn load() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let asset = block_on(cache.load_async::<X>("test.cache")).unwrap();
+        assert_eq!(*asset.read(), X(42));
+        assert!(asset.ptr_eq(&cache.load_cached("test.cache").unwrap()));
+
+        assert!(block_on(cache.load_async::<X>("test.a")).is_err());
+        assert!(block_on(cache.load_async::<X>("test.missing")).is_err());
+    }
+
+    #[test]
+    fn load_dir() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let dir = block_on(cache.load_dir_async::<X>("test")).unwrap();
+        let mut loaded: Vec<_> = dir.iter().map(|x| x.read().0).collect();
+        loaded.sort();
+        assert_eq!(loaded, [-7, 42]);
+
+        assert!(block_on(cache.load_dir_async::<X>("missing")).is_err());
+    }
+
+    #[test]
+    fn deduplicate() {
+        let memory = Memory::new();
+        memory.insert("a", "x", "1");
+        let source = Gated::new(memory);
+        let cache = AssetCache::with_source(source.clone()).unwrap();
+
+        let mut load_1 = Box::pin(cache.load_async::<X>("a"));
+        let mut load_2 = Box::pin(cache.load_async::<X>("a"));
+        assert!(poll_once(&mut load_1).is_pending());
+        assert!(poll_once(&mut load_2).is_pending());
+
+        source.open();
+        let asset_1 = block_on(load_1).unwrap();
+        let asset_2 = block_on(load_2).unwrap();
+        assert!(asset_1.ptr_eq(&asset_2));
+        assert_eq!(source.reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn shared_error() {
+        let source = Gated::new(Memory::new());
+        let cache = AssetCache::with_source(source.clone()).unwrap();
+
+        let mut load_1 = Box::pin(cache.load_async::<X>("a"));
+        let mut load_2 = Box::pin(cache.load_async::<X>("a"));
+        assert!(poll_once(&mut load_1).is_pending());
+        assert!(poll_once(&mut load_2).is_pending());
+
+        source.open();
+        let err_1 = block_on(load_1).unwrap_err();
+        let err_2 = block_on(load_2).unwrap_err();
+        assert!(Arc::ptr_eq(&err_1, &err_2));
+        assert_eq!(source.reads.load(Ordering::SeqCst), 1);
+        assert!(cache.pending.lock().is_empty());
+
+        // Errors are not cached
+        assert!(block_on(cache.load_async::<X>("a")).is_err());
+        assert_eq!(source.reads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn dropped_future() {
+        let source = Gated::new(Memory::new());
+        source.inner.insert("a", "x", "1");
+        let cache = AssetCache::with_source(source.clone()).unwrap();
+
+        let mut load = Box::pin(cache.load_async::<X>("a"));
+        assert!(poll_once(&mut load).is_pending());
+        assert_eq!(cache.pending.lock().len(), 1);
+
+        drop(load);
+        assert!(cache.pending.lock().is_empty());
+
+        source.open();
+        let asset = block_on(cache.load_async::<X>("a")).unwrap();
+        assert_eq!(*asset.read(), X(1));
+        assert!(cache.pending.lock().is_empty());
+    }
+
+    #[test]
+    fn panic_is_not_cached() {
+        use std::{panic, sync::atomic::AtomicBool};
+
+        /// A source that panics on its first read.
+        struct Panicking(Memory, AtomicBool);
+
+        impl Source for Panicking {
+            fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+                if !self.1.swap(true, Ordering::SeqCst) {
+                    panic!("first read");
+                }
+                self.0.read(id, ext)
+            }
+
+            fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+                self.0.read_dir(id, f)
+            }
+
+            fn exists(&self, entry: DirEntry) -> bool {
+                self.0.exists(entry)
+            }
+        }
+
+        let memory = Memory::new();
+        memory.insert("a", "x", "1");
+        let cache = AssetCache::with_source(Panicking(memory, AtomicBool::new(false))).unwrap();
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| block_on(cache.load_async::<X>("a"))));
+        assert!(result.is_err());
+
+        let asset = block_on(cache.load_async::<X>("a")).unwrap();
+        assert_eq!(*asset.read(), X(1));
+    }
+
+    #[test]
+    fn bounded_threads() {
+        use std::{thread, time::Duration};
+
+        /// A source that records the maximum number of concurrent reads.
+        struct Concurrent {
+            inner: Memory,
+            /// The current and maximum numbers of reads.
+            reads: Mutex<(usize, usize)>,
+        }
+
+        impl Source for Concurrent {
+            fn read(&self, id: &str, ext: &str) -> io::Result<FileContent<'_>> {
+                {
+                    let mut reads = self.reads.lock().unwrap();
+                    reads.0 += 1;
+                    reads.1 = reads.1.max(reads.0);
+                }
+                thread::sleep(Duration::from_millis(1));
+                self.reads.lock().unwrap().0 -= 1;
+                self.inner.read(id, ext)
+            }
+
+            fn read_dir(&self, id: &str, f: &mut dyn FnMut(DirEntry)) -> io::Result<()> {
+                self.inner.read_dir(id, f)
+            }
+
+            fn exists(&self, entry: DirEntry) -> bool {
+                self.inner.exists(entry)
+            }
+        }
+
+        let inner = Memory::new();
+        for i in 0..200 {
+            inner.insert(&format!("dir.{}", i), "x", i.to_string());
+        }
+        let source = Concurrent { inner, reads: Mutex::new((0, 0)) };
+        let cache = AssetCache::with_source(source).unwrap();
+
+        let dir = block_on(cache.load_dir_async::<X>("dir")).unwrap();
+        assert_eq!(dir.iter().count(), 200);
+
+        #[cfg(feature = "rayon")]
+        let limit = rayon::current_num_threads();
+        #[cfg(not(feature = "rayon"))]
+        let limit = 8;
+        assert!(cache.source().reads.lock().unwrap().1 <= limit);
+    }
+
+    #[test]
+    fn futures_are_send() {
+        fn is_send<T: Send>(_: T) {}
+
+        let cache = AssetCache::new("assets").unwrap();
+        is_send(cache.load_async::<X>("test.cache"));
+        is_send(cache.load_dir_async::<X>("test"));
+    }
+}
+
+mod batch {
+    use crate::{AssetCache, LoadingBatch};
+    use super::X;
+    use std::sync::Arc;
+
+    #[test]
+    fn load() {
+        let cache = Arc::new(AssetCache::new("assets").unwrap());
+
+        let mut batch = LoadingBatch::new(cache.clone());
+        batch.threads(2);
+        batch.add::<X>("test.a").add::<X>("test.b").add::<X>("test.cache").add::<X>("test.missing");
+        assert_eq!(batch.len(), 4);
+
+        let progress = batch.start();
+        progress.wait();
+        assert!(progress.is_done());
+        assert_eq!(progress.total(), 4);
+        assert_eq!(progress.completed(), 2);
+        assert_eq!(progress.failed(), 2);
+
+        let mut failed: Vec<_> = progress.errors().into_iter().map(|(id, _)| id).collect();
+        failed.sort();
+        assert_eq!(failed, [Box::from("test.a"), Box::from("test.missing")]);
+
+        assert_eq!(*cache.load_cached::<X>("test.b").unwrap().read(), X(-7));
+        assert_eq!(*cache.load_cached::<X>("test.cache").unwrap().read(), X(42));
+    }
+
+    #[test]
+    fn empty() {
+        let cache = Arc::new(AssetCache::new("assets").unwrap());
+
+        let progress = LoadingBatch::new(cache).start();
+        progress.wait();
+        assert!(progress.is_done());
+        assert_eq!(progress.total(), 0);
+    }
+}
+
+mod compound {
+    use crate::{AssetCache, Compound, source::Source};
+    use super::X;
+
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    struct Sum(i32);
+
+    impl Compound for Sum {
+        fn load<S: Source>(cache: &AssetCache<S>, id: &str) -> Result<Sum, Error> {
+            let sum = cache.load_dir::<X>(id)?.iter().map(|x| x.read().0).sum();
+            Ok(Sum(sum))
+        }
+    }
+
+    struct Pair(i32, i32);
+
+    impl Compound for Pair {
+        fn load<S: Source>(cache: &AssetCache<S>, id: &str) -> Result<Pair, Error> {
+            let a = cache.load::<X>(&format!("{}.a", id))?.read().0;
+            let b = cache.load::<X>(&format!("{}.b", id))?.read().0;
+            Ok(Pair(a, b))
+        }
+    }
+
+    #[test]
+    fn load() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        let sum = cache.load_compound::<Sum>("test").unwrap();
+        assert_eq!(sum.read().0, 35);
+
+        let other = cache.load_compound::<Sum>("test").unwrap();
+        assert!(sum.ptr_eq(&other));
+    }
+
+    #[test]
+    fn load_assets() {
+        let source = crate::source::Memory::new();
+        source.insert("pair.a", "x", "1");
+        source.insert("pair.b", "x", "2");
+        let cache = AssetCache::with_source(source).unwrap();
+
+        let pair = cache.load_compound::<Pair>("pair").unwrap();
+        let pair = pair.read();
+        assert_eq!((pair.0, pair.1), (1, 2));
+    }
+
+    #[test]
+    fn error() {
+        let cache = AssetCache::new("assets").unwrap();
+
+        // `test.a` contains an invalid value
+        assert!(cache.load_compound::<Pair>("test").is_err());
+        assert!(cache.load_compound::<Sum>("test.missing").is_err());
+    }
+}
+
+mod query {
+    use crate::{AssetCache, dirs, source::Memory};
+    use super::X;
+
+    fn source() -> Memory {
+        let source = Memory::new();
+        source.insert("ui.menu.icon_play", "x", "1");
+        source.insert("ui.menu.icon_quit", "x", "2");
+        source.insert("ui.menu.button", "x", "3");
+        source.insert("ui.menu.sub.icon_back", "x", "4");
+        source.insert("ui.icon_main", "x", "5");
+        source.insert("fonts.icon_font", "x", "6");
+        source
+    }
+
+    #[test]
+    fn glob_matches() {
+        assert!(dirs::glob_matches("a.b", "a.b"));
+        assert!(!dirs::glob_matches("a.b", "a.b.c"));
+        assert!(dirs::glob_matches("a.*", "a.b"));
+        assert!(!dirs::glob_matches("a.*", "a.b.c"));
+        assert!(dirs::glob_matches("a.*_x*", "a.b_x"));
+        assert!(dirs::glob_matches("a.*_x*", "a._xyz"));
+        assert!(!dirs::glob_matches("a.*_x*", "a.b_y"));
+        assert!(dirs::glob_matches("a.**.c", "a.c"));
+        assert!(dirs::glob_matches("a.**.c", "a.b.b.c"));
+        assert!(!dirs::glob_matches("a.**.c", "a.b.d"));
+        assert!(dirs::glob_matches("**", "a.b"));
+
+        assert_eq!(dirs::glob_root("a.b.*.c"), "a.b");
+        assert_eq!(dirs::glob_root("a.b*.c"), "a");
+        assert_eq!(dirs::glob_root("a.b.c"), "a.b");
+        assert_eq!(dirs::glob_root("*.c"), "");
+    }
+
+    #[test]
+    fn load_glob() {
+        let cache = AssetCache::with_source(source()).unwrap();
+
+        let icons = cache.load_glob::<X>("ui.*.icon_*").unwrap();
+        let ids: Vec<_> = icons.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["ui.menu.icon_play", "ui.menu.icon_quit"]);
+
+        let icons = cache.load_glob::<X>("**.icon_*").unwrap();
+        let values: Vec<_> = icons.iter().map(|x| x.read().0).collect();
+        assert_eq!(values, [6, 5, 1, 2, 4]);
+
+        assert!(cache.load_glob::<X>("missing.*").is_err());
+    }
+
+    #[test]
+    fn load_filtered() {
+        let cache = AssetCache::with_source(source()).unwrap();
+
+        let icons = cache.load_filtered::<X, _>("ui", |id| id.contains("icon_")).unwrap();
+        let ids: Vec<_> = icons.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["ui.icon_main", "ui.menu.icon_play", "ui.menu.icon_quit", "ui.menu.sub.icon_back"]);
+
+        let buttons = cache.load_filtered::<X, _>("ui", |id| id.ends_with("button")).unwrap();
+        let ids: Vec<_> = buttons.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["ui.menu.button"]);
+    }
+
+    #[test]
+    fn filter_with_function() {
+        fn is_button(id: &str) -> bool {
+            id.ends_with("button")
+        }
+
+        let cache = AssetCache::with_source(source()).unwrap();
+        let buttons = cache.load_filtered::<X, _>("ui", is_button).unwrap();
+        let ids: Vec<_> = buttons.iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["ui.menu.button"]);
+    }
+}
+
+mod budget {
+    use crate::{AssetCache, source::Memory};
+    use super::X;
+
+    fn cache() -> AssetCache<Memory> {
+        let source = Memory::new();
+        source.insert("a", "x", "10");
+        source.insert("b", "x", "20");
+        source.insert("c", "x", "30");
+        source.insert("big", "x", "123456");
+        AssetCache::with_source(source).unwrap()
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut cache = cache();
+        assert_eq!(cache.memory_budget(), None);
+
+        cache.load::<X>("a").unwrap();
+        cache.load_handle::<X>("big").unwrap();
+        assert_eq!(cache.memory_usage(), 8);
+
+        cache.remove::<X>("a");
+        assert_eq!(cache.memory_usage(), 6);
+        assert_eq!(cache.take::<X>("big"), Some(X(123456)));
+        assert_eq!(cache.memory_usage(), 0);
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = cache();
+        cache.set_memory_budget(Some(4));
+        assert_eq!(cache.memory_budget(), Some(4));
+
+        cache.load_handle::<X>("a").unwrap();
+        cache.load_handle::<X>("b").unwrap();
+        cache.load_handle::<X>("a").unwrap();
+        cache.load_handle::<X>("c").unwrap();
+
+        assert_eq!(cache.memory_usage(), 4);
+        assert!(cache.load_cached::<X>("b").is_none());
+        assert!(cache.load_cached::<X>("a").is_some());
+        assert!(cache.load_cached::<X>("c").is_some());
+    }
+
+    #[test]
+    fn handles_prevent_eviction() {
+        let cache = cache();
+
+        let handle = cache.load_handle::<X>("a").unwrap();
+        cache.set_memory_budget(Some(0));
+        assert_eq!(cache.memory_usage(), 2);
+
+        drop(handle);
+        cache.set_memory_budget(Some(0));
+        assert_eq!(cache.memory_usage(), 0);
+
+        // Evicted assets are loaded again
+        assert_eq!(*cache.load_handle::<X>("a").unwrap().read(), X(10));
+    }
+
+    #[test]
+    fn evict_borrowed_assets() {
+        let mut cache = cache();
+        cache.set_memory_budget(Some(4));
+
+        {
+            let a = cache.load::<X>("a").unwrap();
+            cache.load::<X>("b").unwrap();
+            cache.load::<X>("c").unwrap();
+
+            assert_eq!(cache.memory_usage(), 4);
+            assert!(cache.load_cached::<X>("a").is_none());
+            assert_eq!(cache.retired_memory_usage(), 2);
+
+            // The `AssetRef` is still valid
+            assert_eq!(*a.read(), X(10));
+
+            // An asset is not evicted when it is loaded
+            cache.load::<X>("big").unwrap();
+            assert_eq!(cache.memory_usage(), 6);
+            assert!(cache.load_cached::<X>("big").is_some());
+            assert_eq!(cache.retired_memory_usage(), 6);
+        }
+
+        assert_eq!(cache.trim(), 1);
+        assert_eq!(cache.retired_memory_usage(), 0);
+        assert_eq!(cache.memory_usage(), 0);
+    }
+
+    #[test]
+    fn trim() {
+        let mut cache = cache();
+
+        let handle = cache.load_handle::<X>("b").unwrap();
+        cache.load::<X>("a").unwrap();
+        cache.set_memory_budget(Some(0));
+        assert_eq!(cache.memory_usage(), 2);
+        assert_eq!(cache.retired_memory_usage(), 2);
+
+        assert_eq!(cache.trim(), 0);
+        assert_eq!(cache.retired_memory_usage(), 0);
+        assert_eq!(cache.memory_usage(), 2);
+        assert_eq!(*handle.read(), X(20));
+    }
+}
+
+mod garbage {
+    use crate::{AssetCache, source::Memory};
+    use super::X;
+
+    #[test]
+    fn collect_garbage() {
+        let source = Memory::new();
+        source.insert("dir.a", "x", "1");
+        source.insert("dir.b", "x", "2");
+        source.insert("c", "x", "3");
+        let mut cache = AssetCache::with_source(source).unwrap();
+
+        cache.load_dir::<X>("dir").unwrap();
+        let handle = cache.load_handle::<X>("dir.a").unwrap();
+        cache.load::<X>("c").unwrap();
+        assert_eq!(cache.memory_usage(), 3);
+
+        assert_eq!(cache.collect_garbage(), 2);
+        assert_eq!(cache.memory_usage(), 1);
+        assert!(cache.load_cached::<X>("dir.b").is_none());
+        assert!(cache.load_cached::<X>("c").is_none());
+
+        let cached = cache.load_cached::<X>("dir.a").unwrap();
+        assert!(cached.ptr_eq(&handle.as_asset_ref()));
+
+        // Directories are loaded again
+        let ids: Vec<_> = cache.load_dir::<X>("dir").unwrap().iter_all().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["dir.a", "dir.b"]);
+
+        drop(handle);
+        assert_eq!(cache.collect_garbage(), 2);
+        assert_eq!(cache.memory_usage(), 0);
+    }
+}
+
+mod unload {
+    use crate::{AssetCache, source::Memory};
+    use super::X;
+    use std::{sync::Arc, thread};
+
+    fn cache() -> AssetCache<Memory> {
+        let source = Memory::new();
+        for i in 0..10 {
+            source.insert(&format!("dir.{}", i), "x", i.to_string());
+        }
+        AssetCache::with_source(source).unwrap()
+    }
+
+    #[test]
+    fn borrowed_assets_are_retired() {
+        let mut cache = cache();
+
+        let asset = cache.load::<X>("dir.1").unwrap();
+        assert!(cache.unload::<X>("dir.1"));
+        assert!(!cache.unload::<X>("dir.1"));
+        assert!(cache.load_cached::<X>("dir.1").is_none());
+        assert_eq!(cache.memory_usage(), 0);
+
+        // The `AssetRef` is still valid
+        assert_eq!(*asset.read(), X(1));
+        assert_eq!(cache.retired.lock().assets.len(), 1);
+        assert_eq!(cache.retired_memory_usage(), 1);
+
+        cache.remove::<X>("dir.1");
+        assert_eq!(cache.retired.lock().assets.len(), 0);
+        assert_eq!(cache.retired_memory_usage(), 0);
+    }
+
+    #[test]
+    fn handles_are_not_retired() {
+        let cache = cache();
+
+        let handle = cache.load_handle::<X>("dir.2").unwrap();
+        assert!(cache.unload::<X>("dir.2"));
+        assert_eq!(cache.retired.lock().assets.len(), 0);
+        assert_eq!(cache.retired_memory_usage(), 0);
+        assert_eq!(*handle.read(), X(2));
+
+        let other = cache.load_handle::<X>("dir.2").unwrap();
+        assert!(!other.ptr_eq(&handle));
+    }
+
+    #[test]
+    fn unload_all() {
+        let mut cache = cache();
+
+        let dir = cache.load_dir::<X>("dir").unwrap();
+        cache.load_handle::<X>("dir.0").unwrap();
+        cache.unload_all();
+
+        assert_eq!(cache.memory_usage(), 0);
+        assert!(cache.load_cached::<X>("dir.3").is_none());
+        assert_eq!(dir.iter().count(), 0);
+
+        // Assets of the directory are loaded again
+        assert_eq!(dir.iter_all().count(), 10);
+
+        let retired = cache.retired.lock();
+        assert_eq!((retired.assets.len(), retired.dirs.len()), (10, 1));
+        drop(retired);
+        assert_eq!(cache.retired_memory_usage(), 10);
+
+        cache.clear();
+        assert_eq!(cache.retired.lock().assets.len(), 0);
+        assert_eq!(cache.retired_memory_usage(), 0);
+    }
+
+    #[test]
+    fn concurrent_unload() {
+        let cache = Arc::new(cache());
+
+        let threads: Vec<_> = (0..8).map(|t| {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                for i in 0..200 {
+                    let id = format!("dir.{}", (i + t) % 10);
+                    let handle = cache.load_handle::<X>(&id).unwrap();
+                    if i % 3 == 0 {
+                        cache.unload::<X>(&id);
+                    }
+                    assert_eq!(handle.read().0, (i + t) % 10);
+                }
+            })
+        }).collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        cache.unload_all();
+        assert_eq!(cache.memory_usage(), 0);
+        assert_eq!(cache.retired.lock().assets.len(), 0);
+    }
+}
+
+mod sharded {
+    use crate::{AssetCache, source::Memory};
+    use super::X;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn concurrent_loads() {
+        let source = Memory::new();
+        for i in 0..100 {
+            source.insert(&format!("dir.{}", i), "x", i.to_string());
+        }
+        let cache = Arc::new(AssetCache::with_source(source).unwrap());
+
+        let threads: Vec<_> = (0..16).map(|t| {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                (0..500).map(|i| {
+                    let n = (i * 7 + t * 13) % 100;
+                    let id = format!("dir.{}", n);
+
+                    match i % 3 {
+                        0 => assert_eq!(cache.load::<X>(&id).unwrap().read().0, n),
+                        1 => if let Some(x) = cache.load_cached::<X>(&id) {
+                            assert_eq!(x.read().0, n);
+                        },
+                        _ => {},
+                    }
+
+                    let handle = cache.load_handle::<X>(&id).unwrap();
+                    assert_eq!(handle.read().0, n);
+                    (n, handle)
+                }).collect::<Vec<_>>()
+            })
+        }).collect();
+
+        let expected: Vec<_> = (0..100)
+            .map(|i| cache.load_handle::<X>(&format!("dir.{}", i)).unwrap())
+            .collect();
+
+        for thread in threads {
+            for (n, handle) in thread.join().unwrap() {
+                assert!(handle.ptr_eq(&expected[n as usize]));
+            }
+        }
+
+        assert_eq!(cache.load_dir::<X>("dir").unwrap().iter().count(), 100);
+    }
 }
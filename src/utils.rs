@@ -29,6 +29,7 @@ impl<T> RwLock<T> {
         Self(sync::RwLock::new(inner))
     }
 
+    #[cfg(not(feature = "arc-swap"))]
     #[inline]
     pub fn into_inner(self) -> T {
         wrap(self.0.into_inner())
@@ -37,15 +38,33 @@ impl<T> RwLock<T> {
 
 impl<T: ?Sized> RwLock<T> {
     #[inline]
-    pub fn read(&self) -> RwLockReadGuard<T> {
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
         wrap(self.0.read())
     }
 
     #[inline]
-    pub fn write(&self) -> RwLockWriteGuard<T> {
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
         wrap(self.0.write())
     }
 
+    /// Locks for writing, or returns `None` if this would block.
+    #[cfg(all(feature = "hot-reloading", feature = "parking_lot"))]
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.0.try_write()
+    }
+
+    /// Locks for writing, or returns `None` if this would block.
+    #[cfg(all(feature = "hot-reloading", not(feature = "parking_lot")))]
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        match self.0.try_write() {
+            Ok(guard) => Some(guard),
+            Err(sync::TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(sync::TryLockError::WouldBlock) => None,
+        }
+    }
+
     #[inline]
     pub fn get_mut(&mut self) -> &mut T {
         wrap(self.0.get_mut())
@@ -53,10 +72,8 @@ impl<T: ?Sized> RwLock<T> {
 }
 
 
-#[cfg(feature = "hot-reloading")]
 pub(crate) struct Mutex<T: ?Sized>(sync::Mutex<T>);
 
-#[cfg(feature = "hot-reloading")]
 impl<T> Mutex<T> {
     #[inline]
     pub fn new(inner: T) -> Self {
@@ -64,10 +81,9 @@ impl<T> Mutex<T> {
     }
 }
 
-#[cfg(feature = "hot-reloading")]
 impl<T: ?Sized> Mutex<T> {
     #[inline]
-    pub fn lock(&self) -> sync::MutexGuard<T> {
+    pub fn lock(&self) -> sync::MutexGuard<'_, T> {
         wrap(self.0.lock())
     }
 
@@ -85,10 +101,10 @@ use std::{
 };
 
 #[cfg(feature = "ahash")]
-use ahash::RandomState;
+pub(crate) use ahash::RandomState;
 
 #[cfg(not(feature = "ahash"))]
-use std::collections::hash_map::RandomState;
+pub(crate) use std::collections::hash_map::RandomState;
 
 pub(crate) struct HashMap<K, V>(StdHashMap<K, V, RandomState>);
 